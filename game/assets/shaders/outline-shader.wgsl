@@ -3,7 +3,8 @@
 struct OutlineMaterial {
     outline_color: vec4<f32>,
     image_tint: vec4<f32>,
-    thickness: f32
+    thickness: f32,
+    fill: f32
 };
 @group(1) @binding(0)
 var<uniform> material: OutlineMaterial;
@@ -33,5 +34,9 @@ fn fragment(
     outline += get_sample(uv + vec2<f32>(-material.thickness, -material.thickness));
     outline = min(outline, 1.0);
     var color: vec4<f32> = textureSample(base_color_texture, base_color_sampler, uv);
-    return mix(color, material.outline_color, outline - color.a) - color * (vec4<f32>(1.0, 1.0, 1.0, 1.0) - material.image_tint);
+    var tint: vec4<f32> = material.image_tint;
+    if (uv.x > material.fill) {
+        tint = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return mix(color, material.outline_color, outline - color.a) - color * (vec4<f32>(1.0, 1.0, 1.0, 1.0) - tint);
 }
\ No newline at end of file
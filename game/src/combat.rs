@@ -0,0 +1,423 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    control::KinematicCharacterControllerOutput,
+    geometry::{Collider, Group, Sensor},
+    plugin::RapierContext,
+};
+
+use crate::{
+    constants::{
+        AppSet, AppState, GameState, ENEMY_GROUP, INVULNERABILITY_DURATION, KNOCKBACK_DURATION,
+        KNOCKBACK_SPEED, PLAYER_GROUP, POISON_TICK,
+    },
+    doors::Door,
+    enemies::Enemy,
+    items::{
+        components::{Bullet, BulletPath, ProjectileKind, Ricochet},
+        projectile::{Pierced, Poison, Projectile, ProjectileStats},
+    },
+    obstacles::Obstacle,
+    player::{Life, Player, SoulHearts},
+};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<EnemyKilled>()
+            .add_systems(
+                Update,
+                (
+                    detect_bullet_hits,
+                    detect_contact_damage,
+                    detect_spike_damage,
+                    tick_poison,
+                    apply_damage,
+                    despawn_dead,
+                    flash_invulnerable,
+                )
+                    .chain()
+                    .in_set(AppSet::Combat)
+                    .after(AppSet::Items)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/**
+ * Side an entity fights for. Projectiles carry the team of whoever fired them
+ * and never hurt their own side.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
+impl Team {
+    /**
+     * Collision group every member of the team is in.
+     */
+    pub fn group(&self) -> Group {
+        match self {
+            Team::Player => PLAYER_GROUP,
+            Team::Enemy => ENEMY_GROUP,
+        }
+    }
+}
+
+/**
+ * How much `Life` a projectile takes away on hit, or an enemy takes away by touching the player.
+ */
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Damage(pub u32);
+
+/**
+ * Sent whenever something with `Life` gets hurt. `origin` is where the hit came from,
+ * targets are knocked back away from it. Damage over time has none.
+ */
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    pub origin: Option<Vec2>,
+}
+
+/**
+ * Sent when an enemy runs out of `Life`, right before it is despawned.
+ */
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub position: Vec2,
+}
+
+/**
+ * Ignores any damage while `current` is above zero. Restarted on every hit taken.
+ */
+#[derive(Component, Default)]
+pub struct Invulnerability {
+    pub current: f32,
+}
+
+/**
+ * Pushes an entity away from whatever hit it. Applied on top of its own movement
+ * until `current` runs out.
+ */
+#[derive(Component, Default)]
+pub struct Knockback {
+    pub velocity: Vec2,
+    pub current: f32,
+}
+
+/**
+ * Hurts an entity every `POISON_TICK` seconds until `remaining` runs out.
+ */
+#[derive(Component)]
+pub struct Poisoned {
+    pub damage: u32,
+    pub remaining: f32,
+    pub tick: f32,
+}
+
+/**
+ * Bullets stop at the first thing they hit: anything with `Life` that is not on their team,
+ * any solid collider such as walls and rocks, or a door whether it is open or not.
+ * Bullets with `Ricochet` bounce off the solid ones instead.
+ *
+ * `ProjectileStats` decide what else happens on a hit: piercing bullets keep going, split
+ * bullets burst into smaller ones and poisonous bullets leave their target `Poisoned`.
+ */
+fn detect_bullet_hits(
+    mut commands: Commands,
+    context: Res<RapierContext>,
+    mut query_bullet: Query<
+        (
+            Entity,
+            &Transform,
+            &Damage,
+            Option<&Team>,
+            Has<Ricochet>,
+            Option<&ProjectileStats>,
+            Option<&mut Pierced>,
+            Option<&Handle<Image>>,
+        ),
+        With<Bullet>,
+    >,
+    query_target: Query<Option<&Team>, With<Life>>,
+    query_solid: Query<(), (With<Collider>, Without<Sensor>, Without<Team>)>,
+    query_door: Query<(), With<Door>>,
+    mut writer: EventWriter<DamageEvent>,
+) {
+    for (bullet, transform, Damage(amount), team, ricochet, stats, mut pierced, texture) in
+        &mut query_bullet
+    {
+        // Sensor bullets only overlap things, physics driven ones touch them.
+        let intersections = context
+            .intersection_pairs_with(bullet)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(a, b, _)| if a == bullet { b } else { a });
+        let contacts = context
+            .contact_pairs_with(bullet)
+            .filter(|pair| pair.has_any_active_contacts())
+            .map(|pair| {
+                if pair.collider1() == bullet {
+                    pair.collider2()
+                } else {
+                    pair.collider1()
+                }
+            });
+
+        let already_hit = |other: &Entity| pierced.as_ref().is_some_and(|p| p.0.contains(other));
+
+        let hit = intersections
+            .chain(contacts)
+            .filter(|other| !already_hit(other))
+            .find(|other| match query_target.get(*other) {
+                Ok(target_team) => team.is_none() || target_team != team,
+                Err(_) => {
+                    !ricochet && (query_solid.contains(*other) || query_door.contains(*other))
+                }
+            });
+
+        let Some(other) = hit else {
+            continue;
+        };
+
+        if !query_target.contains(other) {
+            commands.entity(bullet).despawn_recursive();
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+
+        writer.send(DamageEvent {
+            target: other,
+            amount: *amount,
+            origin: Some(position),
+        });
+
+        let stats = stats.copied().unwrap_or_default();
+
+        if let Some(Poison { damage, duration }) = stats.poison {
+            commands.entity(other).insert(Poisoned {
+                damage,
+                remaining: duration,
+                tick: POISON_TICK,
+            });
+        }
+
+        if let (Some(team), Some(texture)) = (team, texture) {
+            if stats.split > 0 {
+                Projectile {
+                    position,
+                    direction: transform.right().truncate(),
+                    team: *team,
+                    damage: *amount,
+                    kind: ProjectileKind::Scripted,
+                    path: BulletPath::Straight,
+                    stats: stats.split_into(),
+                    texture: texture.clone(),
+                    pierced: vec![other],
+                }
+                .spawn_burst(&mut commands, stats.split);
+            }
+        }
+
+        match pierced.as_deref_mut() {
+            Some(Pierced(targets)) if (targets.len() as u32) < stats.piercing => {
+                targets.push(other);
+            }
+            _ => {
+                commands.entity(bullet).despawn_recursive();
+            }
+        }
+    }
+}
+
+/**
+ * Deals poison damage on every tick, without an origin so it doesn't knock the target back.
+ */
+fn tick_poison(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Poisoned)>,
+    mut writer: EventWriter<DamageEvent>,
+) {
+    for (entity, mut poisoned) in &mut query {
+        poisoned.remaining -= time.delta_seconds();
+        poisoned.tick -= time.delta_seconds();
+
+        if poisoned.tick <= 0. {
+            poisoned.tick += POISON_TICK;
+            writer.send(DamageEvent {
+                target: entity,
+                amount: poisoned.damage,
+                origin: None,
+            });
+        }
+
+        if poisoned.remaining <= 0. {
+            commands.entity(entity).remove::<Poisoned>();
+        }
+    }
+}
+
+/**
+ * Enemies hurt the player by touching it, whether the player walked into them or the other way around.
+ */
+fn detect_contact_damage(
+    query_player: Query<(Entity, Option<&KinematicCharacterControllerOutput>), With<Player>>,
+    query_enemy: Query<
+        (
+            Entity,
+            &Transform,
+            &Damage,
+            Option<&KinematicCharacterControllerOutput>,
+        ),
+        With<Enemy>,
+    >,
+    mut writer: EventWriter<DamageEvent>,
+) {
+    let Ok((player, player_output)) = query_player.get_single() else {
+        return;
+    };
+
+    let touched_by_player = |enemy: Entity| {
+        player_output.is_some_and(|output| {
+            output
+                .collisions
+                .iter()
+                .any(|collision| collision.entity == enemy)
+        })
+    };
+
+    for (enemy, transform, Damage(amount), output) in &query_enemy {
+        let touched_player = output.is_some_and(|output| {
+            output
+                .collisions
+                .iter()
+                .any(|collision| collision.entity == player)
+        });
+
+        if touched_player || touched_by_player(enemy) {
+            writer.send(DamageEvent {
+                target: player,
+                amount: *amount,
+                origin: Some(transform.translation.truncate()),
+            });
+        }
+    }
+}
+
+/**
+ * Standing on spikes hurts, as often as the player's invulnerability lets it.
+ */
+fn detect_spike_damage(
+    context: Res<RapierContext>,
+    query_player: Query<Entity, With<Player>>,
+    query_obstacle: Query<&Obstacle>,
+    mut writer: EventWriter<DamageEvent>,
+) {
+    let Ok(player) = query_player.get_single() else {
+        return;
+    };
+
+    let amount = context
+        .intersection_pairs_with(player)
+        .filter(|(_, _, intersecting)| *intersecting)
+        .map(|(a, b, _)| if a == player { b } else { a })
+        .filter_map(|other| query_obstacle.get(other).ok()?.damage())
+        .max();
+
+    if let Some(amount) = amount {
+        writer.send(DamageEvent {
+            target: player,
+            amount,
+            origin: None,
+        });
+    }
+}
+
+fn apply_damage(
+    mut reader: EventReader<DamageEvent>,
+    mut query: Query<(
+        &mut Life,
+        &Transform,
+        Option<&mut Invulnerability>,
+        Option<&mut Knockback>,
+        Option<&mut SoulHearts>,
+    )>,
+) {
+    for event in reader.read() {
+        if let Ok((mut life, transform, invulnerability, knockback, soul_hearts)) =
+            query.get_mut(event.target)
+        {
+            if let Some(mut invulnerability) = invulnerability {
+                if invulnerability.current > 0. {
+                    continue;
+                }
+
+                invulnerability.current = INVULNERABILITY_DURATION;
+            }
+
+            if let (Some(mut knockback), Some(origin)) = (knockback, event.origin) {
+                let direction = (transform.translation.truncate() - origin).normalize_or_zero();
+                knockback.velocity = direction * KNOCKBACK_SPEED;
+                knockback.current = KNOCKBACK_DURATION;
+            }
+
+            let mut amount = event.amount;
+
+            if let Some(mut soul_hearts) = soul_hearts {
+                let absorbed = amount.min(soul_hearts.0);
+                soul_hearts.0 -= absorbed;
+                amount -= absorbed;
+            }
+
+            life.0 = life.0.saturating_sub(amount);
+        }
+    }
+}
+
+/**
+ * Removes enemies and breakables once they run out of `Life`. The player is left alone.
+ */
+fn despawn_dead(
+    mut commands: Commands,
+    query: Query<(Entity, &Life, &Transform, Has<Enemy>), (Changed<Life>, Without<Player>)>,
+    mut writer: EventWriter<EnemyKilled>,
+) {
+    for (entity, Life(life), transform, is_enemy) in &query {
+        if *life == 0 {
+            if is_enemy {
+                writer.send(EnemyKilled {
+                    position: transform.translation.truncate(),
+                });
+            }
+
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/**
+ * Blinks the sprite while invulnerable so the player can tell hits are being ignored.
+ */
+fn flash_invulnerable(time: Res<Time>, mut query: Query<(&mut Invulnerability, &mut Visibility)>) {
+    for (mut invulnerability, mut visibility) in &mut query {
+        if invulnerability.current <= 0. {
+            continue;
+        }
+
+        invulnerability.current -= time.delta_seconds();
+
+        *visibility =
+            if invulnerability.current > 0. && (invulnerability.current * 10.) as u32 % 2 == 0 {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+    }
+}
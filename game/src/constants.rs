@@ -1,7 +1,16 @@
 use bevy::prelude::*;
+use bevy_rapier2d::geometry::Group;
 
 pub const PLAYER_SIZE: Vec2 = Vec2::new(64.0, 64.0);
 pub const PLAYER_SPEED: f32 = 500.0;
+pub const INVULNERABILITY_DURATION: f32 = 1.0;
+pub const KNOCKBACK_SPEED: f32 = 900.0;
+pub const KNOCKBACK_DURATION: f32 = 0.15;
+pub const STICK_DEADZONE: f32 = 0.3;
+pub const POISON_TICK: f32 = 1.0;
+
+pub const ENEMY_SIZE: Vec2 = Vec2::new(56.0, 56.0);
+pub const ENEMY_BULLET_SPEED: f32 = 450.0;
 
 pub const WIDTH: f32 = 1920.;
 pub const HEIGHT: f32 = 1080.;
@@ -21,8 +30,42 @@ pub const WALL_WIDTH: f32 = FLOOR_WIDTH / 2. + WALL_THICKNESS;
 
 pub const DOOR_WIDTH: f32 = 120.;
 
+pub const TILE_SIZE: f32 = 80.;
+pub const TILE_COLUMNS: u32 = (FLOOR_WIDTH / TILE_SIZE) as u32;
+pub const TILE_ROWS: u32 = (FLOOR_HEIGHT / TILE_SIZE) as u32;
+
+pub const PEDESTAL_SIZE: Vec2 = Vec2::new(64.0, 64.0);
+pub const STAIRS_SIZE: Vec2 = Vec2::new(80.0, 80.0);
+pub const KEY_SIZE: Vec2 = Vec2::new(24.0, 40.0);
+/// Odds of a combat room dropping a key once cleared.
+pub const KEY_DROP_CHANCE: f32 = 0.35;
+
 pub const WALL_COLOR: Color = Color::rgb(0.01, 0.01, 0.01);
 pub const DOOR_COLOR: Color = Color::rgb(1.0, 0.0, 0.0);
+pub const DOOR_LOCKED_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+pub const DOOR_KEY_LOCKED_COLOR: Color = Color::rgb(0.9, 0.75, 0.1);
+pub const BOSS_DOOR_COLOR: Color = Color::rgb(0.6, 0.1, 0.7);
+pub const BOSS_DOOR_LOCKED_COLOR: Color = Color::rgb(0.25, 0.05, 0.3);
+pub const PEDESTAL_COLOR: Color = Color::rgb(0.45, 0.45, 0.5);
+pub const SHOPKEEPER_COLOR: Color = Color::rgb(0.2, 0.6, 0.3);
+pub const SOUL_HEART_COLOR: Color = Color::rgb(0.45, 0.6, 1.0);
+pub const STAIRS_COLOR: Color = Color::rgb(0.08, 0.06, 0.05);
+pub const KEY_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+pub const ROCK_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+pub const PIT_COLOR: Color = Color::rgb(0.05, 0.03, 0.02);
+pub const SPIKE_COLOR: Color = Color::rgb(0.75, 0.75, 0.8);
+pub const CRATE_COLOR: Color = Color::rgb(0.55, 0.35, 0.15);
+pub const CHASER_COLOR: Color = Color::rgb(0.8, 0.2, 0.2);
+pub const WANDERER_COLOR: Color = Color::rgb(0.6, 0.8, 0.3);
+pub const SHOOTER_COLOR: Color = Color::rgb(0.3, 0.5, 0.9);
+pub const CHARGER_COLOR: Color = Color::rgb(0.9, 0.5, 0.1);
+
+// Pits stop anything walking but let projectiles fly over them
+pub const PIT_GROUP: Group = Group::GROUP_2;
+pub const PROJECTILE_GROUP: Group = Group::GROUP_3;
+// Lets solid bullets pass through whoever fired them
+pub const PLAYER_GROUP: Group = Group::GROUP_4;
+pub const ENEMY_GROUP: Group = Group::GROUP_5;
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum AppState {
@@ -30,6 +73,7 @@ pub enum AppState {
     Splash,
     SetupGame,
     Game,
+    GameOver,
 }
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
@@ -43,4 +87,6 @@ pub enum GameState {
 pub enum AppSet {
     Player,
     Items,
+    Combat,
+    Dungeon,
 }
@@ -1,11 +1,16 @@
-use crate::constants::{self, BOTTOM_WALL, DOOR_WIDTH, LEFT_WALL, RIGHT_WALL, TOP_WALL};
+use crate::constants::{
+    self, BOTTOM_WALL, DOOR_WIDTH, LEFT_WALL, PLAYER_SIZE, RIGHT_WALL, TOP_WALL, WALL_THICKNESS,
+};
+use crate::dungeon::Dungeon;
+use crate::player::{Keys, Player};
 use bevy::{prelude::*, sprite::Anchor};
 use bevy_rapier2d::{
+    control::KinematicCharacterControllerOutput,
     dynamics::RigidBody,
     geometry::{Collider, Sensor},
 };
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Door {
     Left,
     Right,
@@ -13,16 +18,61 @@ pub enum Door {
     Top,
 }
 
+/**
+ * Open doors are sensors the player walks through, any other state makes the door solid.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DoorState {
+    Open,
+    /// Shut while the room still has living enemies.
+    LockedUntilClear,
+    /// Opens when the player walks into it with a key.
+    KeyLocked,
+    /// Leads to or out of the boss room, shut until the room is cleared.
+    Boss {
+        open: bool,
+    },
+}
+
+/**
+ * Door into a secret room, drawn like the wall around it whatever its state.
+ */
+#[derive(Component)]
+pub struct HiddenDoor;
+
 #[derive(Bundle)]
 pub struct DoorBundle {
     door: Door,
-    sensor: Sensor,
+    state: DoorState,
     sprite_bundle: SpriteBundle,
     body: RigidBody,
     collider: Collider,
 }
 
+impl DoorState {
+    pub fn is_open(&self) -> bool {
+        match self {
+            DoorState::Open | DoorState::Boss { open: true } => true,
+            DoorState::LockedUntilClear
+            | DoorState::KeyLocked
+            | DoorState::Boss { open: false } => false,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            DoorState::Open => constants::DOOR_COLOR,
+            DoorState::LockedUntilClear => constants::DOOR_LOCKED_COLOR,
+            DoorState::KeyLocked => constants::DOOR_KEY_LOCKED_COLOR,
+            DoorState::Boss { open: true } => constants::BOSS_DOOR_COLOR,
+            DoorState::Boss { open: false } => constants::BOSS_DOOR_LOCKED_COLOR,
+        }
+    }
+}
+
 impl Door {
+    pub const ALL: [Door; 4] = [Door::Left, Door::Right, Door::Bottom, Door::Top];
+
     fn position(&self) -> Vec2 {
         let wall_position = match self {
             Door::Left => Vec2::new(LEFT_WALL, 0.),
@@ -33,6 +83,41 @@ impl Door {
         wall_position
     }
 
+    /**
+     * Where the player is placed when entering a room through this door,
+     * just inside the wall so it doesn't touch the door straight away.
+     */
+    pub fn spawn_point(&self) -> Vec2 {
+        let inset = WALL_THICKNESS + PLAYER_SIZE.x;
+        match self {
+            Door::Left => Vec2::new(LEFT_WALL + inset, 0.),
+            Door::Right => Vec2::new(RIGHT_WALL - inset, 0.),
+            Door::Bottom => Vec2::new(0., BOTTOM_WALL + inset),
+            Door::Top => Vec2::new(0., TOP_WALL - inset),
+        }
+    }
+
+    /**
+     * Grid offset of the room on the other side of this door.
+     */
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Door::Left => (-1, 0),
+            Door::Right => (1, 0),
+            Door::Bottom => (0, -1),
+            Door::Top => (0, 1),
+        }
+    }
+
+    pub fn opposite(&self) -> Door {
+        match self {
+            Door::Left => Door::Right,
+            Door::Right => Door::Left,
+            Door::Bottom => Door::Top,
+            Door::Top => Door::Bottom,
+        }
+    }
+
     fn anchor(&self) -> Anchor {
         match self {
             Door::Left => Anchor::CenterLeft,
@@ -44,18 +129,18 @@ impl Door {
 }
 
 impl DoorBundle {
-    pub fn new(location: Door) -> DoorBundle {
+    pub fn new(location: Door, state: DoorState) -> DoorBundle {
         let transform = Transform::from_translation(location.position().extend(2.));
         let anchor = location.anchor();
 
         DoorBundle {
             door: location,
-            sensor: Sensor,
+            state,
             sprite_bundle: SpriteBundle {
                 transform,
                 sprite: Sprite {
                     anchor,
-                    color: constants::DOOR_COLOR,
+                    color: state.color(),
                     custom_size: Some(Vec2::splat(DOOR_WIDTH)),
                     ..default()
                 },
@@ -65,4 +150,74 @@ impl DoorBundle {
             collider: Collider::cuboid(DOOR_WIDTH / 2., DOOR_WIDTH / 2.),
         }
     }
+
+    /**
+     * A door that blends into the wall, used for secret room entrances.
+     */
+    pub fn hidden(location: Door, state: DoorState) -> DoorBundle {
+        let mut bundle = DoorBundle::new(location, state);
+        bundle.sprite_bundle.sprite.color = constants::WALL_COLOR;
+        bundle
+    }
+}
+
+/**
+ * Keeps the collider and colour of each door in line with its state.
+ */
+pub fn sync_door_state(
+    mut commands: Commands,
+    mut query: Query<(Entity, &DoorState, &mut Sprite, Has<HiddenDoor>), Changed<DoorState>>,
+) {
+    for (entity, state, mut sprite, hidden) in &mut query {
+        if !hidden {
+            sprite.color = state.color();
+        }
+
+        if state.is_open() {
+            commands.entity(entity).insert(Sensor);
+        } else {
+            commands.entity(entity).remove::<Sensor>();
+        }
+    }
+}
+
+/**
+ * Keeps every door of the current room in line with the dungeon, e.g. opening them once
+ * the room has been cleared. Only doors whose state actually changes are touched.
+ */
+pub fn refresh_door_states(dungeon: Res<Dungeon>, mut query: Query<(&Door, &mut DoorState)>) {
+    if let Some(room) = dungeon.get_current_room() {
+        for (door, mut state) in &mut query {
+            let next = dungeon.door_state(room, *door);
+            if *state != next {
+                *state = next;
+            }
+        }
+    }
+}
+
+pub fn unlock_key_door(
+    mut dungeon: ResMut<Dungeon>,
+    mut query_player: Query<(&KinematicCharacterControllerOutput, &mut Keys), With<Player>>,
+    mut query_door: Query<(&Door, &mut DoorState)>,
+) {
+    if let Ok((output, mut keys)) = query_player.get_single_mut() {
+        for collision in &output.collisions {
+            if let Ok((door, mut state)) = query_door.get_mut(collision.entity) {
+                if *state != DoorState::KeyLocked || keys.0 == 0 {
+                    continue;
+                }
+
+                let (dx, dy) = door.offset();
+                let x = dungeon.current_room_x + dx;
+                let y = dungeon.current_room_y + dy;
+
+                if let Some(room) = dungeon.get_room_mut(x, y) {
+                    room.unlocked = true;
+                    keys.0 -= 1;
+                    *state = DoorState::Open;
+                }
+            }
+        }
+    }
 }
@@ -1,29 +1,742 @@
-use crate::rooms::Room;
+use std::{collections::VecDeque, iter};
+
+use crate::{
+    constants::{AppSet, AppState, KEY_DROP_CHANCE},
+    doors::{refresh_door_states, sync_door_state, unlock_key_door, Door, DoorState},
+    enemies::Enemy,
+    items::{
+        definition::ItemRegistry,
+        pickup::{Pickup, PickupBundle},
+    },
+    player::Player,
+    rng::Rng,
+    room_templates::{load_room_templates, RoomTemplate, RoomTemplateLoader, RoomTemplates},
+    rooms::{Room, RoomEntities, RoomKind},
+};
 use bevy::prelude::*;
 
-#[derive(Component)]
+const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+pub struct DungeonPlugin;
+
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RoomTemplate>()
+            .init_asset_loader::<RoomTemplateLoader>()
+            .init_resource::<PendingRoom>()
+            .init_resource::<RoomEnemies>()
+            .add_event::<RoomEntered>()
+            .add_event::<RoomExited>()
+            .add_event::<RoomCleared>()
+            .add_event::<FloorEntered>()
+            .add_systems(Startup, load_room_templates)
+            .add_systems(OnExit(AppState::Game), teardown_dungeon)
+            .add_systems(
+                Update,
+                (
+                    despawn_exited_room,
+                    queue_entered_room,
+                    queue_reloaded_room,
+                    spawn_pending_room,
+                    // The new room's doors and enemies have to exist before checking on them
+                    apply_deferred,
+                    check_room_cleared,
+                    refresh_door_states,
+                    open_stairs.run_if(on_event::<RoomCleared>()),
+                    drop_key.run_if(on_event::<RoomCleared>()),
+                    unlock_key_door,
+                    sync_door_state,
+                )
+                    .chain()
+                    .in_set(AppSet::Dungeon)
+                    .after(AppSet::Player)
+                    .run_if(resource_exists::<Dungeon>()),
+            );
+    }
+}
+
+/**
+ * Sent when the player arrives in a room. `door` is the side they came in through,
+ * `None` for the start room.
+ */
+#[derive(Event)]
+pub struct RoomEntered {
+    pub coord_x: isize,
+    pub coord_y: isize,
+    pub door: Option<Door>,
+}
+
+/**
+ * Sent when the player leaves a room, before the next room's `RoomEntered`.
+ */
+#[derive(Event)]
+pub struct RoomExited {
+    pub coord_x: isize,
+    pub coord_y: isize,
+}
+
+/**
+ * Sent once when the last enemy of the current room is gone. Rooms without enemies are
+ * cleared as soon as they are entered, with `had_enemies` unset.
+ */
+#[derive(Event)]
+pub struct RoomCleared {
+    pub coord_x: isize,
+    pub coord_y: isize,
+    pub had_enemies: bool,
+}
+
+/**
+ * Sent when a new floor has been generated, including the first one of a run.
+ */
+#[derive(Event)]
+pub struct FloorEntered {
+    pub floor: u32,
+}
+
+/**
+ * Room waiting on its template to be loaded before it can be spawned.
+ */
+#[derive(Resource, Default)]
+struct PendingRoom {
+    coords: Option<(isize, isize)>,
+    place_player: bool,
+}
+
+/**
+ * Whether the room spawned last came with enemies and whether they have shown up yet,
+ * so the room isn't taken for cleared before its enemies ever existed.
+ */
+#[derive(Resource, Default)]
+struct RoomEnemies {
+    spawned: bool,
+    seen: bool,
+}
+
+/**
+ * Parameters for `Dungeon::generate`.
+ */
+#[derive(Clone, Debug)]
+pub struct DungeonConfig {
+    /// Depth of the floor, starting at 1.
+    pub floor: u32,
+    /// Number of rooms to place, including the start and boss rooms.
+    pub room_count: usize,
+    /// Maximum number of neighbours a single room can be connected to (2 to 4).
+    pub branching_factor: usize,
+    /// Chance that a newly placed room is closed off and never grows further.
+    pub dead_end_ratio: f32,
+    pub treasure_rooms: usize,
+    pub shop_rooms: usize,
+    /// Secret rooms are only placed on dead ends, so fewer may end up in the layout.
+    pub secret_rooms: usize,
+}
+
+impl Default for DungeonConfig {
+    fn default() -> Self {
+        DungeonConfig::for_floor(1)
+    }
+}
+
+impl DungeonConfig {
+    /**
+     * Deeper floors are bigger and branch out less, making the way to the boss longer.
+     */
+    pub fn for_floor(floor: u32) -> DungeonConfig {
+        let depth = floor.saturating_sub(1);
+
+        DungeonConfig {
+            floor,
+            room_count: (10 + 3 * depth as usize).min(25),
+            branching_factor: 3,
+            dead_end_ratio: (0.3 - 0.05 * depth as f32).max(0.1),
+            treasure_rooms: 1,
+            shop_rooms: 1,
+            secret_rooms: 1,
+        }
+    }
+}
+
+#[derive(Resource)]
 pub struct Dungeon {
+    /// Seed of the whole run, every floor is generated from it.
+    pub seed: u64,
+    pub floor: u32,
     pub layout: Vec<Room>,
     pub current_room_x: isize,
     pub current_room_y: isize,
 }
 
 impl Dungeon {
-    pub fn new() -> Dungeon {
-        let layout = vec![
-            Room::new(0, 0, "textures/wooden-floor.png".to_string()),
-            Room::new(0, 1, "textures/wooden-floor.png".to_string()),
-        ];
+    /**
+     * Builds a connected grid of rooms starting at (0, 0).
+     * Rooms only ever attach to a single existing room, so the layout is a tree and every
+     * room, including the boss room, is reachable from the start. The start room is always
+     * at (0, 0) and the boss room is the dead end furthest away from it; treasure rooms,
+     * shops and secret rooms prefer the remaining dead ends. The same seed and config
+     * always produce the same layout.
+     */
+    pub fn generate(seed: u64, config: &DungeonConfig) -> Dungeon {
+        // Each floor gets its own layout while still being replayable from the run's seed
+        let mut rng = Rng::new(seed ^ ((config.floor as u64) << 32));
+        let room_count = config.room_count.max(2);
+        let branching_factor = config.branching_factor.clamp(2, 4);
+
+        let mut cells = vec![(0, 0)];
+        let mut closed = vec![false];
+        let mut attempts = 0;
+
+        while cells.len() < room_count && attempts < room_count * 100 {
+            attempts += 1;
+
+            let mut candidates = (0..cells.len())
+                .filter(|i| !closed[*i] && neighbour_count(&cells, cells[*i]) < branching_factor)
+                .filter(|i| can_grow(&cells, cells[*i]))
+                .collect::<Vec<_>>();
+
+            // Everything is closed off or boxed in, grow from anywhere rather than stopping short
+            if candidates.is_empty() {
+                candidates = (0..cells.len())
+                    .filter(|i| can_grow(&cells, cells[*i]))
+                    .collect();
+            }
+
+            if candidates.is_empty() {
+                break;
+            }
+
+            let (x, y) = cells[candidates[rng.below(candidates.len())]];
+            let (dx, dy) = DIRECTIONS[rng.below(DIRECTIONS.len())];
+            let cell = (x + dx, y + dy);
+
+            if cells.contains(&cell) || neighbour_count(&cells, cell) != 1 {
+                continue;
+            }
+
+            cells.push(cell);
+            closed.push(rng.chance(config.dead_end_ratio));
+        }
+
+        let mut kinds = vec![RoomKind::Combat; cells.len()];
+        kinds[0] = RoomKind::Start;
+        kinds[furthest_dead_end(&cells)] = RoomKind::Boss;
+
+        let special_rooms = iter::repeat(RoomKind::Treasure)
+            .take(config.treasure_rooms)
+            .chain(iter::repeat(RoomKind::Shop).take(config.shop_rooms))
+            .chain(iter::repeat(RoomKind::Secret).take(config.secret_rooms));
+
+        for kind in special_rooms {
+            let combat_rooms = (0..cells.len())
+                .filter(|i| kinds[*i] == RoomKind::Combat)
+                .collect::<Vec<_>>();
+            let dead_ends = combat_rooms
+                .iter()
+                .copied()
+                .filter(|i| neighbour_count(&cells, cells[*i]) == 1)
+                .collect::<Vec<_>>();
+
+            let candidates = match kind {
+                RoomKind::Secret => dead_ends,
+                _ if dead_ends.is_empty() => combat_rooms,
+                _ => dead_ends,
+            };
+
+            if !candidates.is_empty() {
+                kinds[candidates[rng.below(candidates.len())]] = kind;
+            }
+        }
+
+        let kind_at = |x: isize, y: isize| {
+            cells
+                .iter()
+                .position(|cell| *cell == (x, y))
+                .map(|i| kinds[i])
+        };
+
+        let layout = cells
+            .iter()
+            .zip(&kinds)
+            .map(|((x, y), kind)| {
+                let doors = Door::ALL
+                    .into_iter()
+                    .filter(|door| {
+                        let (dx, dy) = door.offset();
+                        kind_at(x + dx, y + dy).is_some()
+                    })
+                    .collect::<Vec<_>>();
+
+                let hidden_doors = doors
+                    .iter()
+                    .copied()
+                    .filter(|door| {
+                        let (dx, dy) = door.offset();
+                        *kind != RoomKind::Secret
+                            && kind_at(x + dx, y + dy) == Some(RoomKind::Secret)
+                    })
+                    .collect();
+
+                let templates = kind.templates();
+                let template = templates[rng.below(templates.len())].to_string();
+
+                let mut room = Room::new(*x, *y, *kind, template, doors);
+                room.hidden_doors = hidden_doors;
+                room
+            })
+            .collect();
+
         return Dungeon {
+            seed,
+            floor: config.floor,
             layout,
             current_room_x: 0,
             current_room_y: 0,
         };
     }
 
+    pub fn get_room(&self, x: isize, y: isize) -> Option<&Room> {
+        return self
+            .layout
+            .iter()
+            .find(|room| room.coord_x == x && room.coord_y == y);
+    }
+
+    pub fn get_room_mut(&mut self, x: isize, y: isize) -> Option<&mut Room> {
+        return self
+            .layout
+            .iter_mut()
+            .find(|room| room.coord_x == x && room.coord_y == y);
+    }
+
     pub fn get_current_room(&self) -> Option<&Room> {
-        return self.layout.iter().find(|room| {
-            room.coord_x == self.current_room_x && room.coord_y == self.current_room_y
-        });
+        return self.get_room(self.current_room_x, self.current_room_y);
+    }
+
+    pub fn get_current_room_mut(&mut self) -> Option<&mut Room> {
+        return self.get_room_mut(self.current_room_x, self.current_room_y);
+    }
+
+    pub fn get_boss_room(&self) -> Option<&Room> {
+        return self.layout.iter().find(|room| room.kind == RoomKind::Boss);
+    }
+
+    /**
+     * Boss doors and doors of uncleared rooms stay shut, then treasure rooms need a key
+     * until they have been unlocked once.
+     */
+    pub fn door_state(&self, room: &Room, door: Door) -> DoorState {
+        let (dx, dy) = door.offset();
+        let neighbour = self.get_room(room.coord_x + dx, room.coord_y + dy);
+
+        if room.kind == RoomKind::Boss || neighbour.is_some_and(|n| n.kind == RoomKind::Boss) {
+            return DoorState::Boss { open: room.cleared };
+        }
+
+        if !room.cleared {
+            return DoorState::LockedUntilClear;
+        }
+
+        match neighbour {
+            Some(n) if n.kind == RoomKind::Treasure && !n.unlocked => DoorState::KeyLocked,
+            _ => DoorState::Open,
+        }
+    }
+
+    pub fn neighbours(&self, x: isize, y: isize) -> Vec<&Room> {
+        return self
+            .get_room(x, y)
+            .map(|room| {
+                room.doors
+                    .iter()
+                    .filter_map(|door| {
+                        let (dx, dy) = door.offset();
+                        self.get_room(x + dx, y + dy)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+}
+
+/**
+ * Forgets the finished run's dungeon, its rooms are despawned along with the other
+ * gameplay entities.
+ */
+fn teardown_dungeon(
+    mut commands: Commands,
+    mut pending: ResMut<PendingRoom>,
+    mut enemies: ResMut<RoomEnemies>,
+) {
+    commands.remove_resource::<Dungeon>();
+    *pending = PendingRoom::default();
+    *enemies = RoomEnemies::default();
+}
+
+fn despawn_exited_room(
+    mut commands: Commands,
+    mut reader: EventReader<RoomExited>,
+    query: Query<Entity, With<RoomEntities>>,
+    query_pickup: Query<Entity, With<Pickup>>,
+) {
+    if reader.read().count() == 0 {
+        return;
+    }
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // Items on the floor stay around, the room keeps track of them until the player is back
+    for entity in &query_pickup {
+        commands.entity(entity).remove::<PickupBundle>();
+    }
+}
+
+fn queue_entered_room(
+    mut dungeon: ResMut<Dungeon>,
+    mut pending: ResMut<PendingRoom>,
+    mut reader: EventReader<RoomEntered>,
+) {
+    for event in reader.read() {
+        if let Some(room) = dungeon.get_room_mut(event.coord_x, event.coord_y) {
+            room.visited = true;
+        }
+
+        *pending = PendingRoom {
+            coords: Some((event.coord_x, event.coord_y)),
+            place_player: event.door.is_none(),
+        };
+    }
+}
+
+/**
+ * Respawns the current room when its template changes on disk.
+ */
+fn queue_reloaded_room(
+    mut commands: Commands,
+    dungeon: Res<Dungeon>,
+    templates: Res<RoomTemplates>,
+    mut pending: ResMut<PendingRoom>,
+    mut reader: EventReader<AssetEvent<RoomTemplate>>,
+    query: Query<Entity, With<RoomEntities>>,
+) {
+    let current = dungeon
+        .get_current_room()
+        .and_then(|room| templates.0.get(&room.template));
+
+    for event in reader.read() {
+        if let (AssetEvent::Modified { id }, Some(handle)) = (event, current) {
+            if *id == handle.id() && pending.coords.is_none() {
+                for entity in &query {
+                    commands.entity(entity).despawn_recursive();
+                }
+
+                pending.coords = Some((dungeon.current_room_x, dungeon.current_room_y));
+            }
+        }
+    }
+}
+
+fn spawn_pending_room(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<ItemRegistry>,
+    mut dungeon: ResMut<Dungeon>,
+    templates: Res<RoomTemplates>,
+    room_templates: Res<Assets<RoomTemplate>>,
+    mut pending: ResMut<PendingRoom>,
+    mut enemies: ResMut<RoomEnemies>,
+    mut query_player: Query<&mut Transform, With<Player>>,
+) {
+    if let Some((x, y)) = pending.coords {
+        let template = dungeon
+            .get_room(x, y)
+            .and_then(|room| templates.0.get(&room.template))
+            .and_then(|handle| room_templates.get(handle));
+
+        if let Some(template) = template {
+            let seed = dungeon.seed ^ dungeon.floor as u64;
+            if let Some(room) = dungeon.get_room_mut(x, y) {
+                room.stock_pedestals(&mut commands, &asset_server, &registry, template, seed);
+            }
+        }
+
+        let room = dungeon.get_room(x, y);
+
+        if let (Some(room), Some(template)) = (room, template) {
+            room.spawn(&mut commands, &asset_server, template, &dungeon);
+            *enemies = RoomEnemies {
+                spawned: !room.cleared && !template.enemy_spawners.is_empty(),
+                seen: false,
+            };
+
+            if pending.place_player {
+                for mut transform in &mut query_player {
+                    transform.translation.x = template.player_spawn.x;
+                    transform.translation.y = template.player_spawn.y;
+                }
+            }
+
+            *pending = PendingRoom::default();
+        }
+    }
+}
+
+fn check_room_cleared(
+    mut dungeon: ResMut<Dungeon>,
+    pending: Res<PendingRoom>,
+    mut enemies: ResMut<RoomEnemies>,
+    query_enemy: Query<(), With<Enemy>>,
+    mut writer: EventWriter<RoomCleared>,
+) {
+    if !query_enemy.is_empty() {
+        enemies.seen = true;
+        return;
+    }
+
+    if pending.coords.is_some() || (enemies.spawned && !enemies.seen) {
+        return;
+    }
+
+    if let Some(room) = dungeon.get_current_room_mut() {
+        if !room.cleared {
+            room.cleared = true;
+            writer.send(RoomCleared {
+                coord_x: room.coord_x,
+                coord_y: room.coord_y,
+                had_enemies: enemies.spawned,
+            });
+        }
+    }
+}
+
+/**
+ * Opens the way down once the boss is defeated.
+ */
+fn open_stairs(
+    mut commands: Commands,
+    dungeon: Res<Dungeon>,
+    templates: Res<RoomTemplates>,
+    room_templates: Res<Assets<RoomTemplate>>,
+    mut reader: EventReader<RoomCleared>,
+) {
+    for event in reader.read() {
+        let room = dungeon
+            .get_room(event.coord_x, event.coord_y)
+            .filter(|room| room.kind == RoomKind::Boss);
+        let template = room
+            .and_then(|room| templates.0.get(&room.template))
+            .and_then(|handle| room_templates.get(handle));
+
+        if let (Some(room), Some(template)) = (room, template) {
+            room.spawn_stairs(&mut commands, template);
+        }
+    }
+}
+
+/**
+ * Cleared combat rooms sometimes leave a key behind for the treasure room doors.
+ */
+fn drop_key(
+    mut commands: Commands,
+    mut dungeon: ResMut<Dungeon>,
+    templates: Res<RoomTemplates>,
+    room_templates: Res<Assets<RoomTemplate>>,
+    mut reader: EventReader<RoomCleared>,
+) {
+    for event in reader.read() {
+        let seed = dungeon.seed
+            ^ ((event.coord_x as u64) << 48)
+            ^ ((event.coord_y as u64) << 32)
+            ^ dungeon.floor as u64;
+        let room = dungeon
+            .get_room_mut(event.coord_x, event.coord_y)
+            .filter(|room| room.kind == RoomKind::Combat);
+        let Some(room) = room else {
+            continue;
+        };
+        let template = templates
+            .0
+            .get(&room.template)
+            .and_then(|handle| room_templates.get(handle));
+
+        if let Some(template) = template {
+            if Rng::new(seed).chance(KEY_DROP_CHANCE) {
+                room.key = Some(template.key);
+                Room::spawn_key(&mut commands, template.key);
+            }
+        }
+    }
+}
+
+fn neighbour_count(cells: &[(isize, isize)], (x, y): (isize, isize)) -> usize {
+    return DIRECTIONS
+        .iter()
+        .filter(|(dx, dy)| cells.contains(&(x + dx, y + dy)))
+        .count();
+}
+
+/**
+ * Whether a room could be attached next to `cell` without touching any other room.
+ */
+fn can_grow(cells: &[(isize, isize)], (x, y): (isize, isize)) -> bool {
+    return DIRECTIONS
+        .iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .any(|cell| !cells.contains(&cell) && neighbour_count(cells, cell) == 1);
+}
+
+/**
+ * Index of the dead end with the longest path from the start room,
+ * found with a breadth first search.
+ */
+fn furthest_dead_end(cells: &[(isize, isize)]) -> usize {
+    let start = cells[0];
+    let mut distances = vec![None; cells.len()];
+    let mut queue = VecDeque::from([(start, 0)]);
+    distances[0] = Some(0);
+
+    while let Some(((x, y), distance)) = queue.pop_front() {
+        for (dx, dy) in DIRECTIONS {
+            let next = (x + dx, y + dy);
+            if let Some(i) = cells.iter().position(|cell| *cell == next) {
+                if distances[i].is_none() {
+                    distances[i] = Some(distance + 1);
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+    }
+
+    return (1..cells.len())
+        .filter(|i| neighbour_count(cells, cells[*i]) == 1)
+        .max_by_key(|i| distances[*i].unwrap_or_default())
+        .unwrap_or(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn configs() -> impl Iterator<Item = DungeonConfig> {
+        return (1..=8).map(DungeonConfig::for_floor);
+    }
+
+    /**
+     * Number of doors walked through to get from the start room to every room it leads to.
+     */
+    fn distances(dungeon: &Dungeon) -> HashMap<(isize, isize), usize> {
+        let mut distances = HashMap::from([((0, 0), 0)]);
+        let mut queue = VecDeque::from([((0, 0), 0)]);
+
+        while let Some(((x, y), distance)) = queue.pop_front() {
+            for room in dungeon.neighbours(x, y) {
+                let coords = (room.coord_x, room.coord_y);
+                if !distances.contains_key(&coords) {
+                    distances.insert(coords, distance + 1);
+                    queue.push_back((coords, distance + 1));
+                }
+            }
+        }
+
+        return distances;
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_layout() {
+        let summary = |dungeon: &Dungeon| {
+            dungeon
+                .layout
+                .iter()
+                .map(|room| {
+                    (
+                        (room.coord_x, room.coord_y),
+                        room.kind,
+                        room.template.clone(),
+                        room.doors.clone(),
+                        room.hidden_doors.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for config in configs() {
+            for seed in 0..100 {
+                let first = Dungeon::generate(seed, &config);
+                let second = Dungeon::generate(seed, &config);
+
+                assert_eq!(summary(&first), summary(&second));
+            }
+        }
+    }
+
+    #[test]
+    fn places_as_many_rooms_as_configured() {
+        for config in configs() {
+            for seed in 0..100 {
+                let dungeon = Dungeon::generate(seed, &config);
+
+                assert_eq!(dungeon.layout.len(), config.room_count, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_room_is_reachable_from_the_start() {
+        for config in configs() {
+            for seed in 0..100 {
+                let dungeon = Dungeon::generate(seed, &config);
+                let start = dungeon.get_room(0, 0).expect("no start room");
+
+                assert_eq!(start.kind, RoomKind::Start);
+                assert_eq!(
+                    distances(&dungeon).len(),
+                    dungeon.layout.len(),
+                    "seed {seed}"
+                );
+
+                for room in &dungeon.layout {
+                    for door in &room.doors {
+                        let (dx, dy) = door.offset();
+                        let neighbour = dungeon
+                            .get_room(room.coord_x + dx, room.coord_y + dy)
+                            .expect("door leads nowhere");
+
+                        assert!(neighbour.doors.contains(&door.opposite()));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn boss_is_the_furthest_dead_end() {
+        for config in configs() {
+            for seed in 0..100 {
+                let dungeon = Dungeon::generate(seed, &config);
+                let distances = distances(&dungeon);
+                let distance = |room: &Room| distances[&(room.coord_x, room.coord_y)];
+
+                let bosses = dungeon
+                    .layout
+                    .iter()
+                    .filter(|room| room.kind == RoomKind::Boss)
+                    .collect::<Vec<_>>();
+                assert_eq!(bosses.len(), 1, "seed {seed}");
+
+                let boss = bosses[0];
+                assert_eq!(boss.doors.len(), 1, "seed {seed}");
+
+                let furthest = dungeon
+                    .layout
+                    .iter()
+                    .filter(|room| room.kind != RoomKind::Start && room.doors.len() == 1)
+                    .map(distance)
+                    .max();
+                assert_eq!(Some(distance(boss)), furthest, "seed {seed}");
+            }
+        }
     }
 }
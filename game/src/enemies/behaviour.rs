@@ -0,0 +1,243 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    control::{KinematicCharacterController, KinematicCharacterControllerOutput},
+    dynamics::RigidBody,
+    geometry::{ActiveCollisionTypes, Collider, CollisionGroups, Group, Sensor},
+};
+
+use crate::{
+    combat::{Damage, Team},
+    constants::{ENEMY_BULLET_SPEED, PIT_GROUP, PROJECTILE_GROUP},
+    items::components::{Bullet, Lifetime, Trajectory},
+    player::{Player, Speed},
+    run::GameplayEntity,
+};
+
+use super::{Enemy, EnemyKind, EnemyRng};
+
+/// Distance a shooter tries to keep from the player.
+const KEEP_DISTANCE: f32 = 350.;
+/// How far off the preferred distance a shooter is happy to stand still.
+const KEEP_DISTANCE_MARGIN: f32 = 60.;
+const SHOOTER_RELOAD: f32 = 1.5;
+/// How long a chaser stands still before it starts running at the player.
+const CHASER_IDLE: f32 = 0.6;
+const ENEMY_BULLET_LIFESPAN: f32 = 2.;
+const WIND_UP: f32 = 0.5;
+const CHARGE_DURATION: f32 = 0.6;
+const CHARGE_RECOVERY: f32 = 1.2;
+const CHARGE_SPEED_MULTIPLIER: f32 = 4.;
+
+/**
+ * What an enemy is currently doing. Each `EnemyKind` only ever uses its own subset of states:
+ *
+ * - Chaser: `Idle` -> `Chasing`
+ * - Wanderer: `Wandering`, picking a new direction when the timer runs out or it bumps into something
+ * - Shooter: `KeepingDistance`, firing whenever it has reloaded
+ * - Charger: `Recovering` -> `WindingUp` -> `Charging` -> `Recovering`
+ */
+#[derive(Component, Clone, Copy, Debug)]
+pub enum EnemyState {
+    Idle { timer: f32 },
+    Chasing,
+    Wandering { direction: Vec2, timer: f32 },
+    KeepingDistance { reload: f32 },
+    WindingUp { timer: f32 },
+    Charging { direction: Vec2, timer: f32 },
+    Recovering { timer: f32 },
+}
+
+impl EnemyState {
+    /**
+     * Every enemy gets a moment before attacking, so entering a room is never an instant hit.
+     */
+    pub fn initial(kind: EnemyKind) -> EnemyState {
+        match kind {
+            EnemyKind::Chaser => EnemyState::Idle { timer: CHASER_IDLE },
+            EnemyKind::Wanderer => EnemyState::Wandering {
+                direction: Vec2::ZERO,
+                timer: 0.,
+            },
+            EnemyKind::Shooter => EnemyState::KeepingDistance {
+                reload: SHOOTER_RELOAD,
+            },
+            EnemyKind::Charger => EnemyState::Recovering {
+                timer: CHARGE_RECOVERY,
+            },
+        }
+    }
+}
+
+pub fn update_enemy_state(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    query_player: Query<&Transform, With<Player>>,
+    mut query_enemy: Query<
+        (
+            &mut EnemyState,
+            &mut EnemyRng,
+            &Transform,
+            Option<&KinematicCharacterControllerOutput>,
+        ),
+        With<Enemy>,
+    >,
+) {
+    let Ok(player) = query_player.get_single() else {
+        return;
+    };
+
+    let delta = time.delta_seconds();
+    let target = player.translation.truncate();
+    let texture = asset_server.load::<Image>("textures/bullet.png");
+
+    for (mut state, mut rng, transform, output) in &mut query_enemy {
+        let position = transform.translation.truncate();
+        let to_player = (target - position).normalize_or_zero();
+        let blocked = output.is_some_and(|output| !output.collisions.is_empty());
+
+        *state = match *state {
+            EnemyState::Idle { timer } if timer > 0. => EnemyState::Idle {
+                timer: timer - delta,
+            },
+            EnemyState::Idle { .. } | EnemyState::Chasing => EnemyState::Chasing,
+            EnemyState::Wandering { direction, timer } if timer > 0. && !blocked => {
+                EnemyState::Wandering {
+                    direction,
+                    timer: timer - delta,
+                }
+            }
+            EnemyState::Wandering { .. } => {
+                let EnemyRng(rng) = rng.as_mut();
+
+                // Sometimes stand still for a bit instead of walking off
+                let direction = if rng.chance(0.25) {
+                    Vec2::ZERO
+                } else {
+                    Vec2::from_angle(rng.next_f32() * TAU)
+                };
+
+                EnemyState::Wandering {
+                    direction,
+                    timer: 1. + rng.next_f32() * 2.,
+                }
+            }
+            EnemyState::KeepingDistance { reload } if reload > 0. => EnemyState::KeepingDistance {
+                reload: reload - delta,
+            },
+            EnemyState::KeepingDistance { .. } => {
+                spawn_enemy_bullet(&mut commands, position, to_player, texture.clone());
+
+                EnemyState::KeepingDistance {
+                    reload: SHOOTER_RELOAD,
+                }
+            }
+            EnemyState::Recovering { timer } if timer > 0. => EnemyState::Recovering {
+                timer: timer - delta,
+            },
+            EnemyState::Recovering { .. } => EnemyState::WindingUp { timer: WIND_UP },
+            EnemyState::WindingUp { timer } if timer > 0. => EnemyState::WindingUp {
+                timer: timer - delta,
+            },
+            EnemyState::WindingUp { .. } => EnemyState::Charging {
+                direction: to_player,
+                timer: CHARGE_DURATION,
+            },
+            EnemyState::Charging { direction, timer } if timer > 0. && !blocked => {
+                EnemyState::Charging {
+                    direction,
+                    timer: timer - delta,
+                }
+            }
+            EnemyState::Charging { .. } => EnemyState::Recovering {
+                timer: CHARGE_RECOVERY,
+            },
+        };
+    }
+}
+
+pub fn move_enemies(
+    time: Res<Time>,
+    query_player: Query<&Transform, With<Player>>,
+    mut query_enemy: Query<
+        (
+            &EnemyState,
+            &Speed,
+            &Transform,
+            &mut KinematicCharacterController,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
+) {
+    let Ok(player) = query_player.get_single() else {
+        return;
+    };
+
+    let target = player.translation.truncate();
+
+    for (state, Speed(speed), transform, mut controller) in &mut query_enemy {
+        let position = transform.translation.truncate();
+        let to_player = (target - position).normalize_or_zero();
+
+        let velocity = match *state {
+            EnemyState::Chasing => to_player * *speed,
+            EnemyState::Wandering { direction, .. } => direction * *speed,
+            EnemyState::KeepingDistance { .. } => {
+                let distance = position.distance(target);
+
+                if distance < KEEP_DISTANCE - KEEP_DISTANCE_MARGIN {
+                    -to_player * *speed
+                } else if distance > KEEP_DISTANCE + KEEP_DISTANCE_MARGIN {
+                    to_player * *speed
+                } else {
+                    Vec2::ZERO
+                }
+            }
+            EnemyState::Charging { direction, .. } => direction * *speed * CHARGE_SPEED_MULTIPLIER,
+            EnemyState::Idle { .. }
+            | EnemyState::WindingUp { .. }
+            | EnemyState::Recovering { .. } => Vec2::ZERO,
+        };
+
+        controller.translation = Some(velocity * time.delta_seconds());
+    }
+}
+
+fn spawn_enemy_bullet(
+    commands: &mut Commands,
+    position: Vec2,
+    direction: Vec2,
+    texture: Handle<Image>,
+) {
+    commands.spawn((
+        Bullet,
+        Damage(1),
+        Team::Enemy,
+        GameplayEntity,
+        RigidBody::KinematicPositionBased,
+        Sensor,
+        Collider::ball(8.),
+        CollisionGroups::new(PROJECTILE_GROUP, Group::ALL.difference(PIT_GROUP)),
+        ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+        Trajectory::straight(
+            position,
+            direction,
+            ENEMY_BULLET_SPEED * ENEMY_BULLET_LIFESPAN,
+        ),
+        SpriteBundle {
+            transform: Transform::from_translation(position.extend(2.)),
+            texture,
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(24., 24.)),
+                ..default()
+            },
+            ..default()
+        },
+        Lifetime {
+            current: 0.,
+            lifespan: ENEMY_BULLET_LIFESPAN,
+        },
+    ));
+}
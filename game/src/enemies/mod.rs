@@ -0,0 +1,140 @@
+mod behaviour;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    control::KinematicCharacterController,
+    geometry::{ActiveCollisionTypes, Collider, CollisionGroups, Group},
+};
+use serde::Deserialize;
+
+use crate::{
+    combat::{Damage, Team},
+    constants::{self, AppState, GameState},
+    player::{Life, Speed},
+    rng::Rng,
+};
+
+pub use self::behaviour::EnemyState;
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (behaviour::update_enemy_state, behaviour::move_enemies)
+                .chain()
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/**
+ * Anything hostile. A combat room stays locked while any of these are alive.
+ */
+#[derive(Component)]
+pub struct Enemy;
+
+/**
+ * Decides which behaviour an enemy follows, see `EnemyState` for how each one plays out.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+pub enum EnemyKind {
+    /// Walks straight at the player.
+    #[default]
+    Chaser,
+    /// Roams around the room, ignoring the player.
+    Wanderer,
+    /// Stays at a distance and shoots at the player.
+    Shooter,
+    /// Winds up, then dashes in a straight line towards the player.
+    Charger,
+}
+
+impl EnemyKind {
+    fn life(&self) -> u32 {
+        match self {
+            EnemyKind::Chaser => 3,
+            EnemyKind::Wanderer => 2,
+            EnemyKind::Shooter => 2,
+            EnemyKind::Charger => 4,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match self {
+            EnemyKind::Chaser => 220.,
+            EnemyKind::Wanderer => 150.,
+            EnemyKind::Shooter => 180.,
+            EnemyKind::Charger => 120.,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            EnemyKind::Chaser => constants::CHASER_COLOR,
+            EnemyKind::Wanderer => constants::WANDERER_COLOR,
+            EnemyKind::Shooter => constants::SHOOTER_COLOR,
+            EnemyKind::Charger => constants::CHARGER_COLOR,
+        }
+    }
+}
+
+/**
+ * Randomness for enemy decisions, seeded from the run so rooms play out the same way.
+ */
+#[derive(Component)]
+pub struct EnemyRng(pub Rng);
+
+#[derive(Bundle)]
+pub struct EnemyBundle {
+    enemy: Enemy,
+    kind: EnemyKind,
+    state: EnemyState,
+    rng: EnemyRng,
+    life: Life,
+    speed: Speed,
+    sprite_bundle: SpriteBundle,
+    character_controller: KinematicCharacterController,
+    collider: Collider,
+    active_collision_types: ActiveCollisionTypes,
+    collision_groups: CollisionGroups,
+    team: Team,
+    damage: Damage,
+}
+
+impl EnemyBundle {
+    /**
+     * Enemies get tougher and faster the deeper the floor.
+     */
+    pub fn new(kind: EnemyKind, position: Vec2, floor: u32, seed: u64) -> EnemyBundle {
+        let depth = floor.saturating_sub(1);
+
+        EnemyBundle {
+            enemy: Enemy,
+            kind,
+            state: EnemyState::initial(kind),
+            rng: EnemyRng(Rng::new(seed)),
+            life: Life(kind.life() + depth),
+            speed: Speed(kind.speed() * (1. + 0.1 * depth as f32)),
+            sprite_bundle: SpriteBundle {
+                transform: Transform::from_translation(position.extend(3.)),
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(constants::ENEMY_SIZE),
+                    ..default()
+                },
+                ..default()
+            },
+            character_controller: KinematicCharacterController::default(),
+            collider: Collider::ball(constants::ENEMY_SIZE.x / 2.),
+            // Same as the player, enemies have no rigid body and need to opt in to
+            // being hit by the kinematic bullets.
+            active_collision_types: ActiveCollisionTypes::all(),
+            collision_groups: CollisionGroups::new(Team::Enemy.group(), Group::ALL),
+            team: Team::Enemy,
+            damage: Damage(1),
+        }
+    }
+}
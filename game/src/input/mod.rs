@@ -1,9 +1,15 @@
 use bevy::{
     ecs::system::Res,
-    input::{keyboard::KeyCode, Input},
+    input::{
+        gamepad::{GamepadAxis, GamepadAxisType, Gamepads},
+        keyboard::KeyCode,
+        Axis, Input,
+    },
     math::Vec2,
 };
 
+use crate::constants::STICK_DEADZONE;
+
 pub fn input_as_axis(
     keyboard_input: Res<Input<KeyCode>>,
     left: KeyCode,
@@ -35,3 +41,27 @@ pub fn input_as_axis(
 
     return None;
 }
+
+/**
+ * Direction a stick of any connected gamepad is pushed in, ignoring small movements
+ * below `STICK_DEADZONE`.
+ */
+pub fn stick_as_axis(
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+    x: GamepadAxisType,
+    y: GamepadAxisType,
+) -> Option<Vec2> {
+    for gamepad in gamepads.iter() {
+        let axis = Vec2::new(
+            axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.),
+            axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.),
+        );
+
+        if axis.length() > STICK_DEADZONE {
+            return Some(axis.normalize());
+        }
+    }
+
+    return None;
+}
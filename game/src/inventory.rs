@@ -1,28 +1,390 @@
 use std::collections::VecDeque;
 
 use bevy::{
-    app::{App, Plugin, PostUpdate, Update},
+    app::{App, Plugin, Update},
     ecs::{
-        bundle::Bundle,
         component::Component,
         entity::Entity,
-        event::{event_update_system, Event, EventReader, EventWriter},
-        schedule::{common_conditions::in_state, Condition, IntoSystemConfigs},
-        system::{Commands, Query},
+        event::{Event, EventWriter},
+        query::With,
+        schedule::{common_conditions::in_state, IntoSystemConfigs},
+        system::{Query, Res},
     },
-    math::{Quat, Vec2},
-    prelude::SpatialBundle,
+    input::{keyboard::KeyCode, Input},
     transform::components::Transform,
 };
 
 use crate::{
     constants::{AppSet, AppState, GameState},
+    items::components::ItemEvent,
+    player::Player,
     scenes::console_log,
 };
 
+/// Number of items the revolver cylinder holds.
+pub const REVOLVER_CAPACITY: usize = 4;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InventoryEvent>().add_systems(
+            Update,
+            (cycle_revolver, switch_mode)
+                .in_set(AppSet::Player)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/**
+ * How held items are used:
+ *
+ * - `OneHanded`: a single item, used on its own.
+ * - `DoubleHanded`: one item per hand, both used at once.
+ * - `Revolver`: up to `REVOLVER_CAPACITY` items, only the front one is used.
+ *   The cylinder is turned with its own key.
+ */
 #[derive(Component)]
 pub enum Inventory {
     OneHanded(Option<Entity>),
-    DoubleHanded(Option<Entity>, Entity),
+    DoubleHanded(Option<Entity>, Option<Entity>),
     Revolver(VecDeque<Entity>),
 }
+
+/**
+ * Passive items the player has picked up. They take no inventory slot and can't be dropped,
+ * all they do is add their `Modifiers` to the player's bullets.
+ */
+#[derive(Component, Default)]
+pub struct PassiveItems(pub Vec<Entity>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InventoryMode {
+    OneHanded,
+    DoubleHanded,
+    Revolver,
+}
+
+/**
+ * Sent whenever the content or layout of the player's inventory changes.
+ */
+#[derive(Event)]
+pub enum InventoryEvent {
+    Added(Entity),
+    Removed(Entity),
+    Cycled,
+    ModeChanged(InventoryMode),
+}
+
+impl InventoryMode {
+    pub fn next(&self) -> InventoryMode {
+        match self {
+            InventoryMode::OneHanded => InventoryMode::DoubleHanded,
+            InventoryMode::DoubleHanded => InventoryMode::Revolver,
+            InventoryMode::Revolver => InventoryMode::OneHanded,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn new(mode: InventoryMode) -> Inventory {
+        match mode {
+            InventoryMode::OneHanded => Inventory::OneHanded(None),
+            InventoryMode::DoubleHanded => Inventory::DoubleHanded(None, None),
+            InventoryMode::Revolver => Inventory::Revolver(VecDeque::new()),
+        }
+    }
+
+    pub fn mode(&self) -> InventoryMode {
+        match self {
+            Inventory::OneHanded(_) => InventoryMode::OneHanded,
+            Inventory::DoubleHanded(_, _) => InventoryMode::DoubleHanded,
+            Inventory::Revolver(_) => InventoryMode::Revolver,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            Inventory::OneHanded(_) => 1,
+            Inventory::DoubleHanded(_, _) => 2,
+            Inventory::Revolver(_) => REVOLVER_CAPACITY,
+        }
+    }
+
+    /**
+     * Every held item, in slot order.
+     */
+    pub fn items(&self) -> Vec<Entity> {
+        match self {
+            Inventory::OneHanded(slot) => slot.iter().copied().collect(),
+            Inventory::DoubleHanded(left, right) => left.iter().chain(right).copied().collect(),
+            Inventory::Revolver(entities) => entities.iter().copied().collect(),
+        }
+    }
+
+    /**
+     * Every slot in order, empty ones included.
+     */
+    pub fn slots(&self) -> Vec<Option<Entity>> {
+        match self {
+            Inventory::OneHanded(slot) => vec![*slot],
+            Inventory::DoubleHanded(left, right) => vec![*left, *right],
+            Inventory::Revolver(entities) => (0..REVOLVER_CAPACITY)
+                .map(|index| entities.get(index).copied())
+                .collect(),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.items().len() >= self.capacity();
+    }
+
+    /**
+     * Puts an item in the first free slot, handing it back when there is none.
+     */
+    pub fn add(&mut self, item: Entity) -> Result<(), Entity> {
+        if self.is_full() {
+            return Err(item);
+        }
+
+        match self {
+            Inventory::OneHanded(slot) => *slot = Some(item),
+            Inventory::DoubleHanded(left @ None, _) => *left = Some(item),
+            Inventory::DoubleHanded(_, right) => *right = Some(item),
+            Inventory::Revolver(entities) => entities.push_back(item),
+        }
+
+        return Ok(());
+    }
+
+    /**
+     * Puts an item in the first free slot. When there is none the active item is swapped
+     * out and returned.
+     */
+    pub fn insert(&mut self, item: Entity) -> Option<Entity> {
+        match self.add(item) {
+            Ok(()) => None,
+            Err(item) => {
+                let swapped = self.take_active();
+                self.add(item).ok();
+                swapped
+            }
+        }
+    }
+
+    /**
+     * Removes the active item from the inventory. With both hands full the first one goes.
+     */
+    pub fn take_active(&mut self) -> Option<Entity> {
+        match self {
+            Inventory::OneHanded(slot) => slot.take(),
+            Inventory::DoubleHanded(left, right) => left.take().or_else(|| right.take()),
+            Inventory::Revolver(entities) => entities.pop_front(),
+        }
+    }
+
+    /**
+     * The item that gets used next, the first hand when holding two.
+     */
+    pub fn active(&self) -> Option<Entity> {
+        return self.active_items().first().copied();
+    }
+
+    /**
+     * Every item used at once when the player uses their inventory.
+     */
+    pub fn active_items(&self) -> Vec<Entity> {
+        match self {
+            Inventory::OneHanded(_) | Inventory::DoubleHanded(_, _) => self.items(),
+            Inventory::Revolver(entities) => entities.front().copied().into_iter().collect(),
+        }
+    }
+
+    /**
+     * Turns the revolver to its next item. Does nothing in the other modes.
+     */
+    pub fn cycle(&mut self) {
+        if let Inventory::Revolver(entities) = self {
+            entities.rotate_left(1);
+        }
+    }
+
+    /**
+     * Moves the held items over to another mode, returning the ones that no longer fit.
+     */
+    pub fn set_mode(&mut self, mode: InventoryMode) -> Vec<Entity> {
+        let items = self.items();
+        *self = Inventory::new(mode);
+
+        return items
+            .into_iter()
+            .filter_map(|item| self.add(item).err())
+            .collect();
+    }
+}
+
+fn cycle_revolver(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut Inventory, With<Player>>,
+    mut writer: EventWriter<InventoryEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R) {
+        if let Ok(mut inventory) = query.get_single_mut() {
+            if inventory.mode() == InventoryMode::Revolver {
+                inventory.cycle();
+                writer.send(InventoryEvent::Cycled);
+            }
+        }
+    }
+}
+
+/**
+ * Switches to the next mode, dropping whatever does not fit anymore.
+ */
+fn switch_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(&mut Inventory, &Transform), With<Player>>,
+    mut inventory_writer: EventWriter<InventoryEvent>,
+    mut item_writer: EventWriter<ItemEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        if let Ok((mut inventory, transform)) = query.get_single_mut() {
+            let mode = inventory.mode().next();
+
+            for item in inventory.set_mode(mode) {
+                inventory_writer.send(InventoryEvent::Removed(item));
+                item_writer.send(ItemEvent::Dropped {
+                    entity: item,
+                    position: transform.translation.truncate(),
+                });
+            }
+
+            inventory_writer.send(InventoryEvent::ModeChanged(mode));
+            console_log("Inventory", format!("{:?}", mode));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(count: u32) -> Vec<Entity> {
+        return (0..count).map(Entity::from_raw).collect();
+    }
+
+    fn filled(mode: InventoryMode, items: &[Entity]) -> Inventory {
+        let mut inventory = Inventory::new(mode);
+        for item in items {
+            inventory.add(*item).unwrap();
+        }
+        return inventory;
+    }
+
+    #[test]
+    fn capacity_depends_on_mode() {
+        let extra = Entity::from_raw(100);
+
+        for (mode, capacity) in [
+            (InventoryMode::OneHanded, 1),
+            (InventoryMode::DoubleHanded, 2),
+            (InventoryMode::Revolver, REVOLVER_CAPACITY),
+        ] {
+            let mut inventory = filled(mode, &items(capacity as u32));
+
+            assert_eq!(inventory.capacity(), capacity);
+            assert_eq!(inventory.slots().len(), capacity);
+            assert!(inventory.is_full());
+            assert_eq!(inventory.add(extra), Err(extra));
+        }
+    }
+
+    #[test]
+    fn full_insert_swaps_out_the_active_item() {
+        let [a, b, c] = items(3)[..] else {
+            unreachable!()
+        };
+
+        let mut one_handed = filled(InventoryMode::OneHanded, &[a]);
+        assert_eq!(one_handed.insert(b), Some(a));
+        assert_eq!(one_handed.items(), vec![b]);
+
+        let mut double_handed = filled(InventoryMode::DoubleHanded, &[a, b]);
+        assert_eq!(double_handed.insert(c), Some(a));
+        assert_eq!(double_handed.slots(), vec![Some(c), Some(b)]);
+
+        let held = items(REVOLVER_CAPACITY as u32);
+        let extra = Entity::from_raw(100);
+        let mut revolver = filled(InventoryMode::Revolver, &held);
+        assert_eq!(revolver.insert(extra), Some(held[0]));
+        assert_eq!(revolver.items()[..], [&held[1..], &[extra]].concat()[..]);
+    }
+
+    #[test]
+    fn insert_with_a_free_slot_swaps_nothing() {
+        let [a, b] = items(2)[..] else { unreachable!() };
+        let mut inventory = filled(InventoryMode::DoubleHanded, &[a]);
+
+        assert_eq!(inventory.insert(b), None);
+        assert_eq!(inventory.items(), vec![a, b]);
+    }
+
+    #[test]
+    fn going_from_revolver_to_one_handed_drops_the_rest() {
+        let [a, b, c] = items(3)[..] else {
+            unreachable!()
+        };
+        let mut inventory = filled(InventoryMode::Revolver, &[a, b, c]);
+
+        assert_eq!(inventory.set_mode(InventoryMode::OneHanded), vec![b, c]);
+        assert_eq!(inventory.mode(), InventoryMode::OneHanded);
+        assert_eq!(inventory.items(), vec![a]);
+    }
+
+    #[test]
+    fn going_to_a_bigger_mode_keeps_everything() {
+        let [a, b] = items(2)[..] else { unreachable!() };
+        let mut inventory = filled(InventoryMode::DoubleHanded, &[a, b]);
+
+        assert!(inventory.set_mode(InventoryMode::Revolver).is_empty());
+        assert_eq!(inventory.items(), vec![a, b]);
+    }
+
+    #[test]
+    fn revolver_cycles_to_the_next_item() {
+        let [a, b, c] = items(3)[..] else {
+            unreachable!()
+        };
+        let mut inventory = filled(InventoryMode::Revolver, &[a, b, c]);
+
+        assert_eq!(inventory.active_items(), vec![a]);
+        inventory.cycle();
+        assert_eq!(inventory.active_items(), vec![b]);
+        assert_eq!(inventory.items(), vec![b, c, a]);
+        inventory.cycle();
+        inventory.cycle();
+        assert_eq!(inventory.active(), Some(a));
+    }
+
+    #[test]
+    fn cycle_does_nothing_outside_the_revolver() {
+        let [a, b] = items(2)[..] else { unreachable!() };
+        let mut inventory = filled(InventoryMode::DoubleHanded, &[a, b]);
+
+        inventory.cycle();
+        assert_eq!(inventory.items(), vec![a, b]);
+        assert_eq!(inventory.active_items(), vec![a, b]);
+    }
+
+    #[test]
+    fn take_active_empties_the_first_filled_hand() {
+        let [a, b] = items(2)[..] else { unreachable!() };
+        let mut inventory = filled(InventoryMode::DoubleHanded, &[a, b]);
+
+        assert_eq!(inventory.take_active(), Some(a));
+        assert_eq!(inventory.take_active(), Some(b));
+        assert_eq!(inventory.take_active(), None);
+    }
+}
@@ -1,86 +1,67 @@
-use crate::items::components::{Bullet, Lifetime, Trajectory};
-use bevy::{
-    asset::{AssetServer, Handle},
-    ecs::{
-        component::Component,
-        event::EventReader,
-        system::{Commands, Query, ResMut},
-    },
-    math::{
-        cubic_splines::{CubicBSpline, CubicBezier, CubicCurve, CubicGenerator, CubicSegment},
-        Quat, Vec2, Vec3,
-    },
-    prelude::default,
-    render::texture::Image,
-    sprite::{self, Sprite, SpriteBundle},
-    transform::components::Transform,
+use crate::{
+    combat::Team,
+    items::components::{BulletPath, ProjectileKind},
 };
-use bevy_rapier2d::{
-    dynamics::RigidBody,
-    geometry::{Collider, Sensor},
+use bevy::render::texture::Image;
+
+use super::{
+    definition::{ItemDefinition, ItemUse},
+    projectile::Projectile,
 };
 
-use super::components::ItemEvent;
+/**
+ * Throws a banana in the aim direction. Each way of throwing it is registered as its own
+ * item under its own name.
+ */
+pub struct CasualBulletItem {
+    pub name: &'static str,
+    pub kind: ProjectileKind,
+    pub path: BulletPath,
+}
+
+impl CasualBulletItem {
+    pub const NAME: &'static str = "Casual Bullet";
+
+    pub fn new(name: &'static str, kind: ProjectileKind, path: BulletPath) -> CasualBulletItem {
+        return CasualBulletItem { name, kind, path };
+    }
+}
 
-#[derive(Component)]
-pub struct CasualBulletItem;
+impl Default for CasualBulletItem {
+    fn default() -> Self {
+        CasualBulletItem::new(
+            CasualBulletItem::NAME,
+            ProjectileKind::default(),
+            BulletPath::default(),
+        )
+    }
+}
 
-pub fn use_item(
-    mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
-    mut reader: EventReader<ItemEvent>,
-    query: Query<&CasualBulletItem>,
-) {
-    fn item_used(
-        commands: &mut Commands,
-        position: &Vec2,
-        rotation: &Quat,
-        texture: Handle<Image>,
-    ) {
-        let sprite_bundle = SpriteBundle {
-            transform: Transform {
-                translation: position.extend(2.),
-                rotation: rotation.clone(),
-                ..default()
-            },
-            texture,
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(64., 64.)),
-                ..default()
-            },
-            ..default()
-        };
+impl ItemDefinition for CasualBulletItem {
+    fn name(&self) -> &'static str {
+        self.name
+    }
 
-        commands.spawn((
-            Bullet,
-            RigidBody::KinematicPositionBased,
-            Sensor,
-            Collider::cuboid(0.5, 0.5),
-            Trajectory::straight(
-                position.clone(),
-                sprite_bundle.transform.right().truncate(),
-                1000.,
-            ),
-            sprite_bundle,
-            Lifetime {
-                current: 0.,
-                lifespan: 2.,
-            },
-        ));
+    fn icon(&self) -> &'static str {
+        "textures/banana.png"
     }
 
-    let bullet = asset_server.load::<Image>("textures/banana.png");
+    fn cooldown(&self) -> f32 {
+        0.35
+    }
 
-    for event in reader.read() {
-        if let ItemEvent::Used {
-            entity,
-            position,
-            rotation,
-        } = event
-        {
-            if let Ok(CasualBulletItem) = query.get(*entity) {
-                item_used(&mut commands, position, rotation, bullet.clone());
-            }
+    fn on_use(&self, item: &mut ItemUse) {
+        Projectile {
+            position: item.position,
+            direction: item.direction,
+            team: Team::Player,
+            damage: 1,
+            kind: self.kind,
+            path: self.path,
+            stats: item.stats,
+            texture: item.asset_server.load::<Image>("textures/banana.png"),
+            pierced: vec![],
         }
+        .spawn(item.commands);
     }
 }
@@ -1,6 +1,13 @@
+use std::f32::consts::PI;
+
 use bevy::{
+    asset::Handle,
     ecs::{component::Component, entity::Entity, event::Event},
-    math::{cubic_splines::CubicCurve, Quat, Vec2},
+    math::{
+        cubic_splines::{CubicBSpline, CubicBezier, CubicCurve, CubicGenerator},
+        Vec2,
+    },
+    render::texture::Image,
 };
 
 #[derive(Event)]
@@ -8,39 +15,285 @@ pub enum ItemEvent {
     Used {
         entity: Entity,
         position: Vec2,
-        rotation: Quat,
+        /// Normalized direction the user is aiming in.
+        direction: Vec2,
     },
     Dropped {
         entity: Entity,
         position: Vec2,
     },
+    PickedUp {
+        entity: Entity,
+    },
 }
 
 #[derive(Component)]
 pub struct Item;
 
-#[derive(Component)]
-pub struct Cooldown(f32);
+/**
+ * Time an item needs between two uses. `current` counts down to zero and the item
+ * can only be used again once it gets there.
+ */
+#[derive(Component, Default)]
+pub struct Cooldown {
+    pub current: f32,
+    pub duration: f32,
+}
+
+impl Cooldown {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            current: 0.,
+            duration,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.current <= 0.
+    }
+
+    /**
+     * Restarts the cooldown if the item is ready. Returns whether the item may be used.
+     */
+    pub fn trigger(&mut self) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+
+        self.current = self.duration;
+        true
+    }
+
+    /**
+     * How far along the cooldown is, 1 meaning ready.
+     */
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+
+        1. - (self.current / self.duration).clamp(0., 1.)
+    }
+}
 
 #[derive(Component)]
 pub struct Name(String);
 
+/**
+ * Image shown for the item while it lies on the floor.
+ */
+#[derive(Component)]
+pub struct Icon(pub Handle<Image>);
+
 #[derive(Component, Default)]
 pub struct Bullet;
 
-#[derive(Component, Default)]
-pub struct Trajectory(pub Vec<TrajectorySegment>);
+/**
+ * Path a bullet follows over its `Lifetime`, sampled with the normalized time
+ * `current / lifespan`.
+ *
+ * - `Linear`: straight lines between points, each reached at its own time.
+ * - `Spline`: a cubic curve walked at an even pace between `from` and the end of the lifetime.
+ */
+#[derive(Component)]
+pub enum Trajectory {
+    Linear(Vec<TrajectorySegment>),
+    Spline {
+        curve: CubicCurve<Vec2>,
+        segments: usize,
+        from: f32,
+    },
+}
 
 #[derive(Debug)]
 pub struct TrajectorySegment(pub Vec2, pub f32);
 
 impl Trajectory {
     pub fn straight(start: Vec2, direction: Vec2, length: f32) -> Self {
-        Self(vec![
+        Self::Linear(vec![
             TrajectorySegment(start, 0.),
             TrajectorySegment(start + direction * length, 1.),
         ])
     }
+
+    /**
+     * Bends sideways on the way, peaking `height` away from the straight line halfway through.
+     * A negative `height` bends the other way.
+     */
+    pub fn arc(start: Vec2, direction: Vec2, length: f32, height: f32) -> Self {
+        let forward = direction * length;
+        let side = direction.perp() * height * 4. / 3.;
+
+        Self::bezier(
+            [
+                start,
+                start + forward / 3. + side,
+                start + forward * 2. / 3. + side,
+                start + forward,
+            ],
+            0.,
+        )
+    }
+
+    /**
+     * Wiggles `waves` times across the straight line, `amplitude` away from it at most.
+     * Always wiggles at least once.
+     */
+    pub fn sine(start: Vec2, direction: Vec2, length: f32, amplitude: f32, waves: u32) -> Self {
+        let steps = (waves.max(1) * 4) as i32;
+
+        // A uniform B-spline only reaches 2/3 of a lone peak, and it passes through the ends
+        // when they get a mirrored point on the outside.
+        let points = (-1..=steps + 1)
+            .map(|step| {
+                let progress = step as f32 / steps as f32;
+                let offset = (step as f32 * PI / 2.).sin() * amplitude * 1.5;
+
+                start + direction * length * progress + direction.perp() * offset
+            })
+            .collect::<Vec<_>>();
+
+        return Self::Spline {
+            curve: CubicBSpline::new(points).to_curve(),
+            segments: steps as usize,
+            from: 0.,
+        };
+    }
+
+    /**
+     * Flies out `length` away, swerves `width` to the side and comes back to `start`.
+     */
+    pub fn boomerang(start: Vec2, direction: Vec2, length: f32, width: f32) -> Self {
+        let forward = direction * length * 4. / 3.;
+        let side = direction.perp() * width;
+
+        Self::bezier(
+            [start, start + forward + side, start + forward - side, start],
+            0.,
+        )
+    }
+
+    /**
+     * A single cubic bezier from the first to the last point, walked between `from` and 1.
+     */
+    pub fn bezier(points: [Vec2; 4], from: f32) -> Self {
+        Self::Spline {
+            curve: CubicBezier::new([points]).to_curve(),
+            segments: 1,
+            from,
+        }
+    }
+
+    /**
+     * Where the bullet is at normalized time `t`, or `None` before the first point.
+     */
+    pub fn position(&self, t: f32) -> Option<Vec2> {
+        match self {
+            Trajectory::Linear(points) => {
+                let (mut prev, mut next) = (None, None);
+
+                for point in points {
+                    prev = next;
+                    next = Some(point);
+
+                    if t <= point.1 {
+                        break;
+                    }
+                }
+
+                let (Some(from), Some(to)) = (prev, next) else {
+                    // Right on the first point, there is nothing to interpolate from yet
+                    return next.filter(|point| t >= point.1).map(|point| point.0);
+                };
+
+                let progress = (t - from.1) / (to.1 - from.1);
+
+                return Some(from.0.lerp(to.0, progress));
+            }
+            Trajectory::Spline {
+                curve,
+                segments,
+                from,
+            } => {
+                let progress = ((t - from) / (1. - from)).clamp(0., 1.);
+
+                return Some(curve.position(progress * *segments as f32));
+            }
+        }
+    }
+}
+
+/**
+ * Bends a bullet's remaining `Trajectory` towards the closest enemy of its team.
+ * `strength` is the share of the distance between the original end point and the target
+ * that is corrected every fixed update.
+ */
+#[derive(Component)]
+pub struct Homing {
+    pub strength: f32,
+}
+
+/**
+ * How a bullet moves:
+ *
+ * - `Scripted`: along a `Trajectory`, passing through everything but their targets.
+ * - `Physics`: driven by Rapier, launched fast enough to cover their range over their lifetime.
+ *   They bounce off walls and obstacles, keeping `restitution` of their speed on every bounce,
+ *   so anything below 1 slows them down and they fall short of their range.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ProjectileKind {
+    #[default]
+    Scripted,
+    Physics {
+        restitution: f32,
+    },
+}
+
+/**
+ * Bullets with this bounce off solid colliders instead of stopping at them.
+ */
+#[derive(Component, Default)]
+pub struct Ricochet;
+
+/**
+ * The shape of the path a scripted bullet follows.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub enum BulletPath {
+    #[default]
+    Straight,
+    Arc {
+        height: f32,
+    },
+    Sine {
+        amplitude: f32,
+        waves: u32,
+    },
+    Boomerang {
+        width: f32,
+    },
+    Homing {
+        strength: f32,
+    },
+}
+
+impl BulletPath {
+    pub fn trajectory(&self, start: Vec2, direction: Vec2, length: f32) -> Trajectory {
+        match *self {
+            BulletPath::Straight | BulletPath::Homing { .. } => {
+                Trajectory::straight(start, direction, length)
+            }
+            BulletPath::Arc { height } => Trajectory::arc(start, direction, length, height),
+            BulletPath::Sine { amplitude, waves } => {
+                Trajectory::sine(start, direction, length, amplitude, waves)
+            }
+            // Out and back again covers about the same distance as a straight shot.
+            BulletPath::Boomerang { width } => {
+                Trajectory::boomerang(start, direction, length / 2., width)
+            }
+        }
+    }
 }
 
 #[derive(Component, Default)]
@@ -48,3 +301,80 @@ pub struct Lifetime {
     pub current: f32,
     pub lifespan: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(position: Option<Vec2>, expected: Vec2) {
+        let position = position.expect("trajectory has no position");
+        assert!(
+            position.abs_diff_eq(expected, 1e-3),
+            "expected {expected}, got {position}"
+        );
+    }
+
+    #[test]
+    fn linear_goes_from_point_to_point() {
+        let trajectory = Trajectory::straight(Vec2::ZERO, Vec2::X, 100.);
+
+        assert_near(trajectory.position(0.), Vec2::ZERO);
+        assert_near(trajectory.position(0.5), Vec2::new(50., 0.));
+        assert_near(trajectory.position(1.), Vec2::new(100., 0.));
+    }
+
+    #[test]
+    fn linear_has_no_position_before_its_first_point() {
+        let trajectory = Trajectory::Linear(vec![
+            TrajectorySegment(Vec2::ZERO, 0.5),
+            TrajectorySegment(Vec2::X, 1.),
+        ]);
+
+        assert_eq!(trajectory.position(0.25), None);
+    }
+
+    #[test]
+    fn spline_is_walked_from_its_start_time() {
+        let trajectory = Trajectory::bezier([Vec2::ZERO, Vec2::X, Vec2::X * 2., Vec2::X * 3.], 0.5);
+
+        assert_near(trajectory.position(0.25), Vec2::ZERO);
+        assert_near(trajectory.position(0.75), Vec2::new(1.5, 0.));
+        assert_near(trajectory.position(1.), Vec2::new(3., 0.));
+    }
+
+    #[test]
+    fn arc_peaks_halfway() {
+        let trajectory = Trajectory::arc(Vec2::ZERO, Vec2::X, 100., 20.);
+
+        assert_near(trajectory.position(0.), Vec2::ZERO);
+        assert_near(trajectory.position(0.5), Vec2::new(50., 20.));
+        assert_near(trajectory.position(1.), Vec2::new(100., 0.));
+    }
+
+    #[test]
+    fn sine_ends_on_the_line_and_reaches_its_amplitude() {
+        let trajectory = Trajectory::sine(Vec2::ZERO, Vec2::X, 100., 10., 2);
+
+        assert_near(trajectory.position(0.), Vec2::ZERO);
+        assert_near(trajectory.position(0.125), Vec2::new(12.5, 10.));
+        assert_near(trajectory.position(0.5), Vec2::new(50., 0.));
+        assert_near(trajectory.position(1.), Vec2::new(100., 0.));
+    }
+
+    #[test]
+    fn sine_without_waves_still_wiggles_once() {
+        let trajectory = Trajectory::sine(Vec2::ZERO, Vec2::X, 100., 10., 0);
+
+        assert_near(trajectory.position(0.25), Vec2::new(25., 10.));
+        assert_near(trajectory.position(1.), Vec2::new(100., 0.));
+    }
+
+    #[test]
+    fn boomerang_comes_back() {
+        let trajectory = Trajectory::boomerang(Vec2::ONE, Vec2::Y, 100., 30.);
+
+        assert_near(trajectory.position(0.), Vec2::ONE);
+        assert_near(trajectory.position(0.5), Vec2::new(1., 101.));
+        assert_near(trajectory.position(1.), Vec2::ONE);
+    }
+}
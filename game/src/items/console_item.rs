@@ -1,21 +1,26 @@
 use crate::scenes::console_log;
-use bevy::ecs::{component::Component, event::EventReader, system::Query};
 
-use super::components::ItemEvent;
+use super::definition::{ItemDefinition, ItemUse};
 
-#[derive(Component)]
+/**
+ * Prints its message to the debug console when used.
+ */
 pub struct ConsoleItem(pub String);
 
-pub fn use_item(query: Query<&ConsoleItem>, mut reader: EventReader<ItemEvent>) {
-    fn item_used(ConsoleItem(string): &ConsoleItem) {
-        console_log("Console Item", string);
+impl ConsoleItem {
+    pub const NAME: &'static str = "Console Item";
+}
+
+impl ItemDefinition for ConsoleItem {
+    fn name(&self) -> &'static str {
+        ConsoleItem::NAME
+    }
+
+    fn icon(&self) -> &'static str {
+        "textures/bullet.png"
     }
 
-    for event in reader.read() {
-        if let ItemEvent::Used { entity, .. } = event {
-            if let Ok(item) = query.get(*entity) {
-                item_used(item);
-            }
-        }
+    fn on_use(&self, _item: &mut ItemUse) {
+        console_log("Console Item", &self.0);
     }
 }
@@ -0,0 +1,202 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{player::Player, run::GameplayEntity};
+
+use super::{
+    components::{Cooldown, Icon, Item, ItemEvent},
+    projectile::{Modifier, Modifiers, ProjectileStats},
+};
+
+/**
+ * How hard an item is to come by.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+/**
+ * Everything an item gets to work with when it is used.
+ */
+pub struct ItemUse<'a, 'w, 's> {
+    pub commands: &'a mut Commands<'w, 's>,
+    pub asset_server: &'a AssetServer,
+    /// The item entity being used.
+    pub entity: Entity,
+    pub position: Vec2,
+    pub direction: Vec2,
+    /// The user's bullet stats, with the `Modifiers` of all their items.
+    pub stats: ProjectileStats,
+}
+
+/**
+ * Describes a kind of item. Once registered with `App::register_item` it can be spawned
+ * through the `ItemRegistry`, and using it calls `on_use`.
+ */
+pub trait ItemDefinition: Send + Sync + 'static {
+    /// Unique among all items, it identifies the item in the registry.
+    fn name(&self) -> &'static str;
+
+    /// Path of the image shown in the inventory and on the floor.
+    fn icon(&self) -> &'static str;
+
+    /// Seconds between two uses.
+    fn cooldown(&self) -> f32 {
+        0.
+    }
+
+    fn rarity(&self) -> Rarity {
+        Rarity::Common
+    }
+
+    /// What the item adds to its holder's bullets.
+    fn modifiers(&self) -> Vec<Modifier> {
+        vec![]
+    }
+
+    /// Passive items are never used, they go to the player's `PassiveItems` instead of
+    /// taking an inventory slot.
+    fn is_passive(&self) -> bool {
+        false
+    }
+
+    fn on_use(&self, item: &mut ItemUse);
+}
+
+/**
+ * Marks items spawned from a passive `ItemDefinition`.
+ */
+#[derive(Component)]
+pub struct Passive;
+
+/**
+ * Which `ItemDefinition` an item entity was spawned from.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ItemId(pub &'static str);
+
+#[derive(Resource, Default)]
+pub struct ItemRegistry {
+    definitions: HashMap<&'static str, Box<dyn ItemDefinition>>,
+}
+
+impl ItemRegistry {
+    /**
+     * Panics if an item with the same name is already registered, as one would silently
+     * replace the other.
+     */
+    pub fn register(&mut self, definition: impl ItemDefinition) {
+        let name = definition.name();
+
+        if self.definitions.contains_key(name) {
+            panic!("An item named \"{name}\" is already registered");
+        }
+
+        self.definitions.insert(name, Box::new(definition));
+    }
+
+    /**
+     * Names of every registered item, sorted so picking from them stays deterministic.
+     */
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = self.definitions.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        return names;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ItemDefinition> {
+        return self
+            .definitions
+            .get(name)
+            .map(|definition| definition.as_ref());
+    }
+
+    /**
+     * Spawns an item entity for a registered definition, with its cooldown, icon and modifiers.
+     */
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        name: &str,
+    ) -> Option<Entity> {
+        let definition = self.get(name)?;
+
+        let mut entity = commands.spawn((
+            Item,
+            ItemId(definition.name()),
+            definition.rarity(),
+            Cooldown::new(definition.cooldown()),
+            Icon(asset_server.load(definition.icon())),
+            Modifiers(definition.modifiers()),
+            GameplayEntity,
+        ));
+
+        if definition.is_passive() {
+            entity.insert(Passive);
+        }
+
+        return Some(entity.id());
+    }
+}
+
+pub trait RegisterItem {
+    fn register_item(&mut self, definition: impl ItemDefinition) -> &mut Self;
+}
+
+impl RegisterItem for App {
+    fn register_item(&mut self, definition: impl ItemDefinition) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ItemRegistry::default)
+            .register(definition);
+
+        return self;
+    }
+}
+
+/**
+ * Hands every `ItemEvent::Used` over to the definition of the item being used.
+ */
+pub fn use_items(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<ItemRegistry>,
+    mut reader: EventReader<ItemEvent>,
+    query: Query<&ItemId>,
+    query_player: Query<&ProjectileStats, With<Player>>,
+    mut cooldowns: Query<&mut Cooldown>,
+) {
+    let stats = query_player.get_single().copied().unwrap_or_default();
+
+    for event in reader.read() {
+        if let ItemEvent::Used {
+            entity,
+            position,
+            direction,
+        } = event
+        {
+            let Some(definition) = query
+                .get(*entity)
+                .ok()
+                .and_then(|ItemId(name)| registry.get(name))
+            else {
+                continue;
+            };
+
+            if super::try_use(&mut cooldowns, *entity) {
+                definition.on_use(&mut ItemUse {
+                    commands: &mut commands,
+                    asset_server: &asset_server,
+                    entity: *entity,
+                    position: *position,
+                    direction: *direction,
+                    stats,
+                });
+            }
+        }
+    }
+}
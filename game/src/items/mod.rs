@@ -1,13 +1,20 @@
 mod casual_bullet_item;
 pub mod components;
 mod console_item;
+pub mod definition;
+mod modifier_item;
+pub mod pickup;
+pub mod projectile;
 
 use bevy::{
     app::{App, FixedUpdate, Plugin, Update},
     ecs::{
         entity::Entity,
-        query::With,
-        schedule::IntoSystemConfigs,
+        query::{With, Without},
+        schedule::{
+            common_conditions::{in_state, resource_exists},
+            IntoSystemConfigs,
+        },
         system::{Commands, Query, Res},
     },
     hierarchy::DespawnRecursiveExt,
@@ -16,60 +23,142 @@ use bevy::{
 };
 pub use casual_bullet_item::CasualBulletItem;
 pub use console_item::ConsoleItem;
+pub use modifier_item::ModifierItem;
 
-use crate::constants::AppSet;
+use crate::{
+    combat::Team,
+    constants::{AppSet, AppState, GameState},
+    dungeon::Dungeon,
+};
 
-use self::components::{ItemEvent, Lifetime, Trajectory};
+use self::{
+    components::{Bullet, Cooldown, Homing, ItemEvent, Lifetime, Trajectory},
+    definition::ItemRegistry,
+};
 
 pub struct ItemsPlugin;
 
 impl Plugin for ItemsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ItemEvent>()
-            .add_systems(FixedUpdate, (move_bullet, kill_bullet))
+            .init_resource::<ItemRegistry>()
+            .add_systems(FixedUpdate, (steer_homing, move_bullet).chain())
+            .add_systems(FixedUpdate, kill_bullet)
+            .add_systems(
+                Update,
+                tick_cooldowns
+                    .before(AppSet::Items)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(in_state(GameState::Running)),
+            )
+            .add_systems(
+                Update,
+                (
+                    pickup::arm_pickups,
+                    pickup::pick_up_items,
+                    pickup::drop_items,
+                    pickup::show_pickup_icon,
+                )
+                    .chain()
+                    .in_set(AppSet::Items)
+                    .after(AppSet::Player)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(resource_exists::<Dungeon>()),
+            )
             .add_systems(
                 Update,
-                (console_item::use_item, casual_bullet_item::use_item)
+                (projectile::update_projectile_stats, definition::use_items)
+                    .chain()
                     .in_set(AppSet::Items)
                     .after(AppSet::Player),
             );
     }
 }
 
-fn move_bullet(
-    mut query: Query<(&mut Transform, &Trajectory, &Lifetime), With<components::Bullet>>,
-) {
-    for (mut transform, Trajectory(points), lifetime) in &mut query {
-        let (mut prev, mut next) = (None, None);
+fn tick_cooldowns(time: Res<Time>, mut query: Query<&mut Cooldown>) {
+    for mut cooldown in &mut query {
+        if !cooldown.is_ready() {
+            cooldown.current -= time.delta_seconds();
+        }
+    }
+}
 
-        let t = lifetime.current / lifetime.lifespan;
+/**
+ * Whether an item can be used right now, starting its cooldown if it can.
+ * Items without a `Cooldown` can always be used.
+ */
+fn try_use(cooldowns: &mut Query<&mut Cooldown>, entity: Entity) -> bool {
+    match cooldowns.get_mut(entity) {
+        Ok(mut cooldown) => cooldown.trigger(),
+        Err(_) => true,
+    }
+}
 
-        for point in points {
-            prev = next;
-            next = Some(point);
+fn move_bullet(mut query: Query<(&mut Transform, &Trajectory, &Lifetime), With<Bullet>>) {
+    for (mut transform, trajectory, lifetime) in &mut query {
+        let t = lifetime.current / lifetime.lifespan;
 
-            if t <= point.1 {
-                break;
-            }
+        if let Some(position) = trajectory.position(t) {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
         }
+    }
+}
 
-        if let (Some(from), Some(to)) = (prev, next) {
-            let time_difference = to.1 - from.1;
+/**
+ * Replaces what is left of a homing bullet's path with a curve that keeps its current heading
+ * but ends a bit closer to the nearest opponent.
+ */
+fn steer_homing(
+    mut query_bullet: Query<
+        (
+            &Transform,
+            &mut Trajectory,
+            &Lifetime,
+            &Homing,
+            Option<&Team>,
+        ),
+        With<Bullet>,
+    >,
+    query_target: Query<(&Transform, &Team), Without<Bullet>>,
+) {
+    for (transform, mut trajectory, lifetime, homing, team) in &mut query_bullet {
+        let t = lifetime.current / lifetime.lifespan;
+        let position = transform.translation.truncate();
 
-            let progress = (t - from.1) / time_difference;
+        let target = query_target
+            .iter()
+            .filter(|(_, target_team)| Some(*target_team) != team)
+            .map(|(target, _)| target.translation.truncate())
+            .min_by(|a, b| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            });
 
-            let position = from.0.lerp(to.0, progress);
+        let (Some(target), Some(now), Some(ahead), Some(end)) = (
+            target,
+            trajectory.position(t),
+            trajectory.position(t + 0.01),
+            trajectory.position(1.),
+        ) else {
+            continue;
+        };
 
-            transform.translation.x = position.x;
-            transform.translation.y = position.y;
+        if t >= 1. {
+            continue;
         }
+
+        let end = end.lerp(target, homing.strength);
+        let heading = (ahead - now).normalize_or_zero() * now.distance(end) / 3.;
+
+        *trajectory = Trajectory::bezier([now, now + heading, end, end], t);
     }
 }
 
 fn kill_bullet(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Lifetime), With<components::Bullet>>,
+    mut query: Query<(Entity, &mut Lifetime), With<Bullet>>,
 ) {
     for (entity, mut lifetime) in &mut query {
         lifetime.current += time.delta_seconds();
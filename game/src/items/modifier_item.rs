@@ -0,0 +1,55 @@
+use super::{
+    definition::{ItemDefinition, ItemUse, Rarity},
+    projectile::Modifier,
+};
+
+/**
+ * A passive item: it takes no inventory slot once picked up and only changes the
+ * player's bullets.
+ */
+pub struct ModifierItem {
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub rarity: Rarity,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl ModifierItem {
+    pub fn new(
+        name: &'static str,
+        icon: &'static str,
+        rarity: Rarity,
+        modifiers: Vec<Modifier>,
+    ) -> ModifierItem {
+        return ModifierItem {
+            name,
+            icon,
+            rarity,
+            modifiers,
+        };
+    }
+}
+
+impl ItemDefinition for ModifierItem {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn icon(&self) -> &'static str {
+        self.icon
+    }
+
+    fn rarity(&self) -> Rarity {
+        self.rarity
+    }
+
+    fn modifiers(&self) -> Vec<Modifier> {
+        self.modifiers.clone()
+    }
+
+    fn is_passive(&self) -> bool {
+        true
+    }
+
+    fn on_use(&self, _item: &mut ItemUse) {}
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::RigidBody,
+    geometry::{Collider, Sensor},
+    plugin::RapierContext,
+};
+
+use crate::{
+    dungeon::Dungeon,
+    inventory::{Inventory, InventoryEvent, PassiveItems},
+    player::Player,
+};
+
+use super::{
+    components::{Icon, ItemEvent},
+    definition::Passive,
+};
+
+const PICKUP_SIZE: Vec2 = Vec2::new(48., 48.);
+
+/**
+ * An item lying on the floor. Items dropped by the player are only `armed` once the player
+ * has stepped off them, so they are not picked straight back up.
+ */
+#[derive(Component)]
+pub struct Pickup {
+    pub armed: bool,
+}
+
+/**
+ * Added to an item entity while it lies in the current room, removed again when it is
+ * picked up or the player leaves the room.
+ */
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pickup: Pickup,
+    sprite_bundle: SpriteBundle,
+    body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+}
+
+impl PickupBundle {
+    pub fn new(position: Vec2, armed: bool) -> PickupBundle {
+        PickupBundle {
+            pickup: Pickup { armed },
+            sprite_bundle: SpriteBundle {
+                transform: Transform::from_translation(position.extend(2.)),
+                sprite: Sprite {
+                    custom_size: Some(PICKUP_SIZE),
+                    ..default()
+                },
+                ..default()
+            },
+            body: RigidBody::Fixed,
+            collider: Collider::ball(PICKUP_SIZE.x / 2.),
+            sensor: Sensor,
+        }
+    }
+}
+
+pub fn show_pickup_icon(mut query: Query<(&Icon, &mut Handle<Image>), Added<Pickup>>) {
+    for (Icon(icon), mut texture) in &mut query {
+        *texture = icon.clone();
+    }
+}
+
+fn touching_player(context: &RapierContext, player: Entity, entity: Entity) -> bool {
+    return context.intersection_pair(player, entity) == Some(true);
+}
+
+pub fn arm_pickups(
+    context: Res<RapierContext>,
+    query_player: Query<Entity, With<Player>>,
+    mut query_pickup: Query<(Entity, &mut Pickup)>,
+) {
+    let Ok(player) = query_player.get_single() else {
+        return;
+    };
+
+    for (entity, mut pickup) in &mut query_pickup {
+        if !pickup.armed && !touching_player(&context, player, entity) {
+            pickup.armed = true;
+        }
+    }
+}
+
+/**
+ * Moves a touched pickup into the player's inventory. When there is no free slot the
+ * held item is dropped in its place. Passive items go to the player's `PassiveItems`.
+ */
+pub fn pick_up_items(
+    mut commands: Commands,
+    context: Res<RapierContext>,
+    mut dungeon: ResMut<Dungeon>,
+    mut query_player: Query<(Entity, &Transform, &mut Inventory, &mut PassiveItems), With<Player>>,
+    query_pickup: Query<(Entity, &Pickup, Has<Passive>)>,
+    mut writer: EventWriter<ItemEvent>,
+    mut inventory_writer: EventWriter<InventoryEvent>,
+) {
+    let Ok((player, transform, mut inventory, mut passive_items)) = query_player.get_single_mut()
+    else {
+        return;
+    };
+
+    for (entity, pickup, passive) in &query_pickup {
+        if !pickup.armed || !touching_player(&context, player, entity) {
+            continue;
+        }
+
+        commands.entity(entity).remove::<PickupBundle>();
+
+        if let Some(room) = dungeon.get_current_room_mut() {
+            room.items.retain(|(item, _)| *item != entity);
+        }
+
+        writer.send(ItemEvent::PickedUp { entity });
+
+        if passive {
+            passive_items.0.push(entity);
+            continue;
+        }
+
+        let swapped = inventory.insert(entity);
+        inventory_writer.send(InventoryEvent::Added(entity));
+
+        if let Some(swapped) = swapped {
+            inventory_writer.send(InventoryEvent::Removed(swapped));
+            writer.send(ItemEvent::Dropped {
+                entity: swapped,
+                position: transform.translation.truncate(),
+            });
+        }
+    }
+}
+
+pub fn drop_items(
+    mut commands: Commands,
+    mut dungeon: ResMut<Dungeon>,
+    mut reader: EventReader<ItemEvent>,
+) {
+    for event in reader.read() {
+        if let ItemEvent::Dropped { entity, position } = event {
+            commands
+                .entity(*entity)
+                .insert(PickupBundle::new(*position, false));
+
+            if let Some(room) = dungeon.get_current_room_mut() {
+                room.items.push((*entity, *position));
+            }
+        }
+    }
+}
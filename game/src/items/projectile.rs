@@ -0,0 +1,400 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::{Ccd, CoefficientCombineRule, GravityScale, LockedAxes, RigidBody, Velocity},
+    geometry::{
+        ActiveCollisionTypes, Collider, CollisionGroups, Friction, Group, Restitution, Sensor,
+    },
+};
+
+use crate::{
+    combat::{Damage, Team},
+    constants::{self, PIT_GROUP, PROJECTILE_GROUP},
+    inventory::{Inventory, PassiveItems},
+    player::Player,
+    run::GameplayEntity,
+};
+
+use super::components::{Bullet, BulletPath, Homing, Lifetime, ProjectileKind, Ricochet};
+
+const BULLET_SIZE: f32 = 64.;
+const BULLET_RANGE: f32 = 1000.;
+const BULLET_LIFESPAN: f32 = 2.;
+const PHYSICS_BULLET_RADIUS: f32 = 8.;
+const PHYSICS_BULLET_OFFSET: f32 = constants::PLAYER_SIZE.x / 4. + PHYSICS_BULLET_RADIUS;
+/// Radians between two bullets of a spread shot.
+const SPREAD_ANGLE: f32 = 0.2;
+/// Lowest the speed and range multipliers go, bullets always fly somewhere.
+const MIN_MULTIPLIER: f32 = 0.1;
+
+/**
+ * A change to the player's bullets granted by holding an item. The modifiers of every held
+ * item are added up into the player's `ProjectileStats`.
+ */
+#[derive(Clone, Copy, Debug)]
+pub enum Modifier {
+    /// Goes through this many more targets before stopping.
+    Piercing(u32),
+    /// Fires this many more bullets, fanned out around the aim direction.
+    Spread(u32),
+    /// Bursts into this many smaller bullets when hitting a target.
+    Split(u32),
+    /// Multiplies the size of bullets and their hitbox.
+    SizeUp(f32),
+    /// Multiplies how fast bullets fly, they still stop at the same range.
+    SpeedUp(f32),
+    /// Multiplies how far bullets fly.
+    RangeUp(f32),
+    /// Adds to the `Homing` strength of scripted bullets.
+    Homing(f32),
+    Poison(Poison),
+}
+
+/**
+ * What an item adds to the player's bullets while it is held.
+ */
+#[derive(Component, Clone, Default)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+/**
+ * Hurts `damage` every `POISON_TICK` seconds for `duration` seconds.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Poison {
+    pub damage: u32,
+    pub duration: f32,
+}
+
+/**
+ * Everything that shapes a bullet, built up from the `Modifiers` of every held item.
+ * Kept on the player and copied onto every bullet fired.
+ */
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ProjectileStats {
+    pub piercing: u32,
+    pub spread: u32,
+    pub split: u32,
+    pub size: f32,
+    pub speed: f32,
+    pub range: f32,
+    pub homing: f32,
+    pub poison: Option<Poison>,
+}
+
+impl Default for ProjectileStats {
+    fn default() -> Self {
+        ProjectileStats {
+            piercing: 0,
+            spread: 0,
+            split: 0,
+            size: 1.,
+            speed: 1.,
+            range: 1.,
+            homing: 0.,
+            poison: None,
+        }
+    }
+}
+
+impl ProjectileStats {
+    /**
+     * Stacks a modifier on top. Counts and homing add up, multipliers multiply,
+     * and poison keeps the strongest damage and the longest duration.
+     * Speed and range never drop below `MIN_MULTIPLIER`, the bullet lifespan divides by speed.
+     */
+    pub fn apply(&mut self, modifier: &Modifier) {
+        match *modifier {
+            Modifier::Piercing(count) => self.piercing += count,
+            Modifier::Spread(count) => self.spread += count,
+            Modifier::Split(count) => self.split += count,
+            Modifier::SizeUp(factor) => self.size *= factor,
+            Modifier::SpeedUp(factor) => self.speed = (self.speed * factor).max(MIN_MULTIPLIER),
+            Modifier::RangeUp(factor) => self.range = (self.range * factor).max(MIN_MULTIPLIER),
+            Modifier::Homing(strength) => self.homing += strength,
+            Modifier::Poison(poison) => {
+                self.poison = Some(match self.poison {
+                    Some(current) => Poison {
+                        damage: current.damage.max(poison.damage),
+                        duration: current.duration.max(poison.duration),
+                    },
+                    None => poison,
+                });
+            }
+        }
+    }
+
+    /**
+     * Stats of the bullets a split bullet bursts into: half as big, half the range,
+     * and they don't split or spread any further.
+     */
+    pub fn split_into(&self) -> ProjectileStats {
+        return ProjectileStats {
+            spread: 0,
+            split: 0,
+            size: self.size / 2.,
+            range: (self.range / 2.).max(MIN_MULTIPLIER),
+            ..*self
+        };
+    }
+}
+
+/**
+ * Targets a bullet already went through, so it doesn't hit them again.
+ */
+#[derive(Component, Default)]
+pub struct Pierced(pub Vec<Entity>);
+
+/**
+ * Everything needed to fire a shot. A single shot turns into several bullets with `Spread`.
+ */
+pub struct Projectile {
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub team: Team,
+    pub damage: u32,
+    pub kind: ProjectileKind,
+    pub path: BulletPath,
+    pub stats: ProjectileStats,
+    pub texture: Handle<Image>,
+    /// Targets the bullets can't hit, like the one a split bullet burst on.
+    pub pierced: Vec<Entity>,
+}
+
+impl Projectile {
+    pub fn spawn(&self, commands: &mut Commands) {
+        let spread = self.stats.spread as f32;
+
+        for n in 0..=self.stats.spread {
+            let angle = (n as f32 - spread / 2.) * SPREAD_ANGLE;
+            let direction = Vec2::from_angle(angle).rotate(self.direction);
+
+            self.spawn_bullet(commands, direction);
+        }
+    }
+
+    /**
+     * Bursts into `count` bullets evenly spread around `direction`.
+     */
+    pub fn spawn_burst(&self, commands: &mut Commands, count: u32) {
+        for n in 0..count {
+            let angle = n as f32 * TAU / count as f32;
+            let direction = Vec2::from_angle(angle).rotate(self.direction);
+
+            self.spawn_bullet(commands, direction);
+        }
+    }
+
+    fn spawn_bullet(&self, commands: &mut Commands, direction: Vec2) {
+        let stats = &self.stats;
+        let range = BULLET_RANGE * stats.range;
+        let lifespan = BULLET_LIFESPAN * stats.range / stats.speed;
+
+        let sprite_bundle = SpriteBundle {
+            transform: Transform {
+                translation: self.position.extend(2.),
+                rotation: Quat::from_rotation_z(Vec2::X.angle_between(direction)),
+                ..default()
+            },
+            texture: self.texture.clone(),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(BULLET_SIZE * stats.size)),
+                ..default()
+            },
+            ..default()
+        };
+
+        let filter = match self.kind {
+            ProjectileKind::Scripted => Group::ALL.difference(PIT_GROUP),
+            // Solid bullets would otherwise knock each other and their shooter around.
+            ProjectileKind::Physics { .. } => {
+                Group::ALL.difference(PIT_GROUP | PROJECTILE_GROUP | self.team.group())
+            }
+        };
+
+        let mut bullet = commands.spawn((
+            Bullet,
+            Damage(self.damage),
+            self.team,
+            *stats,
+            Pierced(self.pierced.clone()),
+            GameplayEntity,
+            CollisionGroups::new(PROJECTILE_GROUP, filter),
+            Lifetime {
+                current: 0.,
+                lifespan,
+            },
+        ));
+
+        match self.kind {
+            ProjectileKind::Scripted => {
+                bullet.insert((
+                    RigidBody::KinematicPositionBased,
+                    Sensor,
+                    // Wide enough not to skip over the thin wall colliders between two fixed
+                    // updates, but no wider than the player so it can be fired along a wall.
+                    Collider::ball(BULLET_SIZE * stats.size / 4.),
+                    ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+                    self.path.trajectory(self.position, direction, range),
+                    sprite_bundle,
+                ));
+
+                let homing = match self.path {
+                    BulletPath::Homing { strength } => strength + stats.homing,
+                    _ => stats.homing,
+                };
+
+                if homing > 0. {
+                    bullet.insert(Homing { strength: homing });
+                }
+            }
+            ProjectileKind::Physics { restitution } => {
+                let radius = PHYSICS_BULLET_RADIUS * stats.size;
+
+                // Spawned in front of the shooter so it doesn't bounce off them right away.
+                let mut sprite_bundle = sprite_bundle;
+                sprite_bundle.transform.translation += (direction
+                    * (PHYSICS_BULLET_OFFSET - PHYSICS_BULLET_RADIUS + radius))
+                    .extend(0.);
+
+                bullet.insert((
+                    RigidBody::Dynamic,
+                    Collider::ball(radius),
+                    Velocity::linear(direction * range / lifespan),
+                    Restitution {
+                        coefficient: restitution,
+                        combine_rule: CoefficientCombineRule::Max,
+                    },
+                    Friction {
+                        coefficient: 0.,
+                        combine_rule: CoefficientCombineRule::Min,
+                    },
+                    GravityScale(0.),
+                    LockedAxes::ROTATION_LOCKED,
+                    Ccd::enabled(),
+                    Ricochet,
+                    sprite_bundle,
+                ));
+            }
+        }
+    }
+}
+
+/**
+ * Adds up the modifiers of every held and passive item whenever either of them changes.
+ */
+pub fn update_projectile_stats(
+    mut query_player: Query<
+        (&Inventory, &PassiveItems, &mut ProjectileStats),
+        (
+            With<Player>,
+            Or<(Changed<Inventory>, Changed<PassiveItems>)>,
+        ),
+    >,
+    query_modifiers: Query<&Modifiers>,
+) {
+    for (inventory, passive_items, mut stats) in &mut query_player {
+        let mut new_stats = ProjectileStats::default();
+        let items = inventory
+            .items()
+            .into_iter()
+            .chain(passive_items.0.iter().copied());
+
+        for Modifiers(modifiers) in query_modifiers.iter_many(items) {
+            for modifier in modifiers {
+                new_stats.apply(modifier);
+            }
+        }
+
+        *stats = new_stats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_with(modifiers: &[Modifier]) -> ProjectileStats {
+        let mut stats = ProjectileStats::default();
+        for modifier in modifiers {
+            stats.apply(modifier);
+        }
+        return stats;
+    }
+
+    #[test]
+    fn counts_and_homing_add_up() {
+        let stats = stats_with(&[
+            Modifier::Piercing(1),
+            Modifier::Piercing(2),
+            Modifier::Spread(2),
+            Modifier::Split(3),
+            Modifier::Homing(0.5),
+            Modifier::Homing(1.),
+        ]);
+
+        assert_eq!(stats.piercing, 3);
+        assert_eq!(stats.spread, 2);
+        assert_eq!(stats.split, 3);
+        assert_eq!(stats.homing, 1.5);
+    }
+
+    #[test]
+    fn multipliers_multiply() {
+        let stats = stats_with(&[
+            Modifier::SizeUp(2.),
+            Modifier::SizeUp(1.5),
+            Modifier::SpeedUp(2.),
+            Modifier::RangeUp(0.5),
+        ]);
+
+        assert_eq!(stats.size, 3.);
+        assert_eq!(stats.speed, 2.);
+        assert_eq!(stats.range, 0.5);
+    }
+
+    #[test]
+    fn speed_and_range_stay_positive() {
+        let stats = stats_with(&[Modifier::SpeedUp(0.), Modifier::RangeUp(-1.)]);
+
+        assert_eq!(stats.speed, MIN_MULTIPLIER);
+        assert_eq!(stats.range, MIN_MULTIPLIER);
+    }
+
+    #[test]
+    fn poison_keeps_the_strongest_of_each() {
+        let stats = stats_with(&[
+            Modifier::Poison(Poison {
+                damage: 1,
+                duration: 5.,
+            }),
+            Modifier::Poison(Poison {
+                damage: 2,
+                duration: 3.,
+            }),
+        ]);
+        let poison = stats.poison.unwrap();
+
+        assert_eq!(poison.damage, 2);
+        assert_eq!(poison.duration, 5.);
+    }
+
+    #[test]
+    fn split_bullets_are_smaller_and_do_not_split_again() {
+        let stats = stats_with(&[
+            Modifier::Split(2),
+            Modifier::Spread(2),
+            Modifier::Piercing(1),
+            Modifier::SizeUp(2.),
+            Modifier::RangeUp(2.),
+        ]);
+        let split = stats.split_into();
+
+        assert_eq!(split.split, 0);
+        assert_eq!(split.spread, 0);
+        assert_eq!(split.piercing, 1);
+        assert_eq!(split.size, 1.);
+        assert_eq!(split.range, 1.);
+        assert_eq!(split.speed, stats.speed);
+    }
+}
@@ -1,22 +1,37 @@
+mod combat;
 mod constants;
 mod doors;
 mod dungeon;
+mod enemies;
 mod input;
 mod inventory;
 mod items;
 mod materials;
+mod obstacles;
 mod player;
+mod rng;
+mod room_templates;
 mod rooms;
+mod run;
 mod scenes;
 
 use bevy::{prelude::*, render::camera::ScalingMode, window::*};
 use bevy_rapier2d::plugin::{NoUserData, RapierPhysicsPlugin};
+use combat::CombatPlugin;
 use constants::{AppState, GameState};
-use inventory::Inventory;
-use items::{components::Item, CasualBulletItem, ConsoleItem, ItemsPlugin};
+use dungeon::{Dungeon, DungeonConfig, DungeonPlugin, FloorEntered, RoomEntered};
+use enemies::EnemyPlugin;
+use inventory::{Inventory, InventoryPlugin};
+use items::{
+    components::{BulletPath, ProjectileKind},
+    definition::{ItemRegistry, Rarity, RegisterItem},
+    projectile::{Modifier, Poison},
+    CasualBulletItem, ConsoleItem, ItemsPlugin, ModifierItem,
+};
 use materials::ShaderPlugin;
 use player::PlayerPlugin;
-use scenes::{DebugOverlay, MainMenu, PauseMenu, PlayerOverlay};
+use run::{NextRun, RunPlugin, RunStats};
+use scenes::{DebugOverlay, GameOverMenu, MainMenu, PauseMenu, PlayerOverlay};
 use styles::elements::StylesPlugin;
 
 fn main() {
@@ -62,6 +77,93 @@ fn main() {
             PlayerOverlay,
             ItemsPlugin,
             PlayerPlugin,
+            DungeonPlugin,
+            EnemyPlugin,
+            CombatPlugin,
+            RunPlugin,
+            GameOverMenu,
+            InventoryPlugin,
+        ))
+        .register_item(CasualBulletItem::default())
+        .register_item(CasualBulletItem::new(
+            "Boomerang Banana",
+            ProjectileKind::Scripted,
+            BulletPath::Boomerang { width: 150. },
+        ))
+        .register_item(CasualBulletItem::new(
+            "Lobbed Banana",
+            ProjectileKind::Scripted,
+            BulletPath::Arc { height: 120. },
+        ))
+        .register_item(CasualBulletItem::new(
+            "Wobbly Banana",
+            ProjectileKind::Scripted,
+            BulletPath::Sine {
+                amplitude: 40.,
+                waves: 3,
+            },
+        ))
+        .register_item(CasualBulletItem::new(
+            "Seeking Banana",
+            ProjectileKind::Scripted,
+            BulletPath::Homing { strength: 0.05 },
+        ))
+        .register_item(CasualBulletItem::new(
+            "Bouncy Banana",
+            ProjectileKind::Physics { restitution: 0.8 },
+            BulletPath::Straight,
+        ))
+        .register_item(ConsoleItem(String::from("Used the console item")))
+        .register_item(ModifierItem::new(
+            "Sharp Peel",
+            "textures/bullet.png",
+            Rarity::Uncommon,
+            vec![Modifier::Piercing(1)],
+        ))
+        .register_item(ModifierItem::new(
+            "Banana Bunch",
+            "textures/banana.png",
+            Rarity::Rare,
+            vec![Modifier::Spread(2)],
+        ))
+        .register_item(ModifierItem::new(
+            "Banana Split",
+            "textures/banana.png",
+            Rarity::Rare,
+            vec![Modifier::Split(3)],
+        ))
+        .register_item(ModifierItem::new(
+            "Plantain",
+            "textures/banana.png",
+            Rarity::Common,
+            vec![Modifier::SizeUp(1.5)],
+        ))
+        .register_item(ModifierItem::new(
+            "Tailwind",
+            "textures/bullet.png",
+            Rarity::Common,
+            vec![Modifier::SpeedUp(1.4)],
+        ))
+        .register_item(ModifierItem::new(
+            "Strong Arm",
+            "textures/bullet.png",
+            Rarity::Common,
+            vec![Modifier::RangeUp(1.5)],
+        ))
+        .register_item(ModifierItem::new(
+            "Cat's Eye",
+            "textures/cat.png",
+            Rarity::Uncommon,
+            vec![Modifier::Homing(0.05)],
+        ))
+        .register_item(ModifierItem::new(
+            "Rotten Banana",
+            "textures/banana.png",
+            Rarity::Uncommon,
+            vec![Modifier::Poison(Poison {
+                damage: 1,
+                duration: 3.,
+            })],
         ))
         .add_systems(Startup, setup_camera)
         .add_systems(OnEnter(AppState::SetupGame), setup_game);
@@ -87,20 +189,34 @@ fn setup_camera(mut commands: Commands) {
 fn setup_game(
     mut commands: Commands,
     mut app_state: ResMut<NextState<AppState>>,
+    mut room_entered: EventWriter<RoomEntered>,
+    mut floor_entered: EventWriter<FloorEntered>,
+    mut next_run: ResMut<NextRun>,
     asset_server: ResMut<AssetServer>,
+    item_registry: Res<ItemRegistry>,
 ) {
     let player = asset_server.load::<Image>("textures/cat.png");
 
-    let casual_bullet_item = commands.spawn((Item, CasualBulletItem)).id();
+    let casual_bullet_item =
+        item_registry.spawn(&mut commands, &asset_server, CasualBulletItem::NAME);
 
     let mut player = commands.spawn(player::PlayerBundle::new(Vec2::ZERO, player));
 
-    player.insert(Inventory::OneHanded(Some(casual_bullet_item)));
+    player.insert(Inventory::OneHanded(casual_bullet_item));
+
+    let seed = next_run.seed.take().unwrap_or_else(rng::random_seed);
+    commands.insert_resource(RunStats::new(seed));
 
-    let d = dungeon::Dungeon::new();
-    d.get_current_room()
-        .unwrap()
-        .spawn(&mut commands, asset_server);
+    let dungeon = Dungeon::generate(seed, &DungeonConfig::default());
+    room_entered.send(RoomEntered {
+        coord_x: dungeon.current_room_x,
+        coord_y: dungeon.current_room_y,
+        door: None,
+    });
+    floor_entered.send(FloorEntered {
+        floor: dungeon.floor,
+    });
+    commands.insert_resource(dungeon);
 
     app_state.set(AppState::Game);
 }
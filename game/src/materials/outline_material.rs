@@ -17,6 +17,9 @@ pub struct OutlineMaterial {
     pub image_tint: Color,
     #[uniform(0)]
     pub thickness: f32,
+    /// Part of the image, from the left, drawn with `image_tint`. The rest is drawn black.
+    #[uniform(0)]
+    pub fill: f32,
     #[texture(1)]
     #[sampler(2)]
     pub color_texture: Handle<Image>,
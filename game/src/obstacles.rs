@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::RigidBody,
+    geometry::{Collider, CollisionGroups, Group},
+};
+use serde::Deserialize;
+
+use crate::constants::{self, PIT_GROUP, TILE_SIZE};
+
+/**
+ * Props placed on the room's tile grid.
+ * Rocks and crates block everything, pits only block walking and spikes can be walked over,
+ * though it hurts. Crates break when shot.
+ */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Obstacle {
+    Rock,
+    Pit,
+    Spike,
+    Crate,
+}
+
+#[derive(Bundle)]
+pub struct ObstacleBundle {
+    obstacle: Obstacle,
+    sprite_bundle: SpriteBundle,
+    body: RigidBody,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+}
+
+impl Obstacle {
+    fn color(&self) -> Color {
+        match self {
+            Obstacle::Rock => constants::ROCK_COLOR,
+            Obstacle::Pit => constants::PIT_COLOR,
+            Obstacle::Spike => constants::SPIKE_COLOR,
+            Obstacle::Crate => constants::CRATE_COLOR,
+        }
+    }
+
+    fn collision_groups(&self) -> CollisionGroups {
+        match self {
+            Obstacle::Pit => CollisionGroups::new(PIT_GROUP, Group::ALL),
+            _ => CollisionGroups::default(),
+        }
+    }
+
+    /**
+     * Whether the obstacle only detects overlaps instead of blocking movement.
+     */
+    pub fn is_sensor(&self) -> bool {
+        return *self == Obstacle::Spike;
+    }
+
+    /**
+     * How much standing on the obstacle hurts the player.
+     */
+    pub fn damage(&self) -> Option<u32> {
+        match self {
+            Obstacle::Spike => Some(1),
+            _ => None,
+        }
+    }
+
+    /**
+     * Breakable obstacles take damage from bullets like enemies do.
+     */
+    pub fn life(&self) -> Option<u32> {
+        match self {
+            Obstacle::Crate => Some(1),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Centre of a tile, counted in columns and rows from the bottom left corner of the floor.
+ */
+pub fn tile_position(column: u32, row: u32) -> Vec2 {
+    return Vec2::new(
+        -constants::FLOOR_WIDTH / 2. + TILE_SIZE * (column as f32 + 0.5),
+        -constants::FLOOR_HEIGHT / 2. + TILE_SIZE * (row as f32 + 0.5),
+    );
+}
+
+impl ObstacleBundle {
+    pub fn new(obstacle: Obstacle, column: u32, row: u32) -> ObstacleBundle {
+        ObstacleBundle {
+            obstacle,
+            sprite_bundle: SpriteBundle {
+                transform: Transform::from_translation(tile_position(column, row).extend(2.)),
+                sprite: Sprite {
+                    color: obstacle.color(),
+                    custom_size: Some(Vec2::splat(TILE_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            body: RigidBody::Fixed,
+            collider: Collider::cuboid(TILE_SIZE / 2., TILE_SIZE / 2.),
+            collision_groups: obstacle.collision_groups(),
+        }
+    }
+}
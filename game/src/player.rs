@@ -1,16 +1,26 @@
 use std::ops::Mul;
 
 use crate::{
+    combat::{Invulnerability, Knockback, Team},
     constants::{self, AppSet, AppState, GameState, PLAYER_SPEED},
     doors::Door,
-    input::input_as_axis,
-    inventory::Inventory,
-    items::components::ItemEvent,
+    dungeon::{Dungeon, DungeonConfig, FloorEntered, RoomEntered, RoomExited},
+    input::{input_as_axis, stick_as_axis},
+    inventory::{Inventory, InventoryEvent, PassiveItems},
+    items::{components::ItemEvent, projectile::ProjectileStats},
+    rooms::{Key, Stairs},
+    run::GameplayEntity,
     scenes::console_log,
 };
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::{
+    input::{common_conditions::input_just_pressed, gamepad::GamepadAxisType},
+    prelude::*,
+    window::CursorMoved,
+};
 use bevy_rapier2d::{
-    control::KinematicCharacterController, geometry::Collider, plugin::RapierContext,
+    control::KinematicCharacterController,
+    geometry::{ActiveCollisionTypes, Collider, CollisionGroups, Group},
+    plugin::RapierContext,
 };
 
 pub struct PlayerPlugin;
@@ -26,11 +36,35 @@ impl Plugin for PlayerPlugin {
                 .run_if(in_state(AppState::Game))
                 .run_if(input_just_pressed(KeyCode::Escape)),
         )
-        .add_systems(Update, use_item_player.in_set(AppSet::Player))
+        .add_event::<PlayerDied>()
         .add_systems(
-            FixedUpdate,
-            (move_player, read_touching_door_system)
+            Update,
+            (aim_player, use_item_player, drop_item_player)
                 .chain()
+                .in_set(AppSet::Player)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(GameState::Running)),
+        )
+        .add_systems(
+            Update,
+            (walk_through_door_system, take_stairs, pick_up_key)
+                .in_set(AppSet::Player)
+                .run_if(in_state(AppState::Game))
+                .run_if(in_state(GameState::Running)),
+        )
+        .add_systems(
+            Update,
+            (
+                clamp_life.after(AppSet::Combat),
+                check_player_died,
+                game_over.run_if(on_event::<PlayerDied>()),
+            )
+                .chain()
+                .run_if(in_state(AppState::Game)),
+        )
+        .add_systems(
+            FixedUpdate,
+            move_player
                 .run_if(in_state(AppState::Game))
                 .run_if(in_state(GameState::Running)),
         );
@@ -41,10 +75,22 @@ impl Plugin for PlayerPlugin {
 pub struct PlayerBundle {
     sprite_bundle: SpriteBundle,
     life: Life,
+    max_life: MaxLife,
+    soul_hearts: SoulHearts,
     player: Player,
     character_controller: KinematicCharacterController,
     collider: Collider,
+    active_collision_types: ActiveCollisionTypes,
+    collision_groups: CollisionGroups,
     speed: Speed,
+    keys: Keys,
+    team: Team,
+    invulnerability: Invulnerability,
+    knockback: Knockback,
+    aim_direction: AimDirection,
+    projectile_stats: ProjectileStats,
+    passive_items: PassiveItems,
+    gameplay_entity: GameplayEntity,
 }
 
 impl PlayerBundle {
@@ -53,7 +99,9 @@ impl PlayerBundle {
 
         PlayerBundle {
             player: Player,
-            life: Life(2),
+            life: Life(6),
+            max_life: MaxLife(6),
+            soul_hearts: SoulHearts(0),
             sprite_bundle: SpriteBundle {
                 transform: Transform::from_translation(position.extend(3.)),
                 texture: image,
@@ -65,20 +113,68 @@ impl PlayerBundle {
             },
             character_controller: KinematicCharacterController::default(),
             collider: Collider::ball(constants::PLAYER_SIZE.x / 4.0),
+            // Without a rigid body the player counts as fixed, so it has to opt in to
+            // overlapping the fixed doors and props.
+            active_collision_types: ActiveCollisionTypes::all(),
+            collision_groups: CollisionGroups::new(Team::Player.group(), Group::ALL),
             speed: Speed(PLAYER_SPEED),
+            keys: Keys(1),
+            team: Team::Player,
+            invulnerability: Invulnerability::default(),
+            knockback: Knockback::default(),
+            aim_direction: AimDirection::default(),
+            projectile_stats: ProjectileStats::default(),
+            passive_items: PassiveItems::default(),
+            gameplay_entity: GameplayEntity,
         }
     }
 }
 
+/**
+ * Hit points. For the player every point is half a heart.
+ */
 #[derive(Component)]
 pub struct Life(pub u32);
 
+/**
+ * Upper bound for the player's `Life`, in half hearts. Two of them make one heart container.
+ */
+#[derive(Component)]
+pub struct MaxLife(pub u32);
+
+/**
+ * Extra half hearts on top of `Life`. They are lost before any `Life` is and cannot be healed back.
+ */
+#[derive(Component)]
+pub struct SoulHearts(pub u32);
+
 #[derive(Component)]
 pub struct Speed(pub f32);
 
 #[derive(Component)]
 pub struct Player;
 
+#[derive(Component)]
+pub struct Keys(pub u32);
+
+/**
+ * Where the player's items are fired, independent of where they walk. Always normalized.
+ */
+#[derive(Component)]
+pub struct AimDirection(pub Vec2);
+
+impl Default for AimDirection {
+    fn default() -> Self {
+        AimDirection(Vec2::X)
+    }
+}
+
+/**
+ * Sent once when the player's `Life` reaches zero.
+ */
+#[derive(Event)]
+pub struct PlayerDied;
+
 fn pause_game(mut game_state: ResMut<NextState<GameState>>) {
     game_state.set(GameState::Paused);
 }
@@ -87,12 +183,36 @@ fn unpause_game(mut game_state: ResMut<NextState<GameState>>) {
     game_state.set(GameState::Running);
 }
 
+fn clamp_life(mut query: Query<(&mut Life, &MaxLife), Or<(Changed<Life>, Changed<MaxLife>)>>) {
+    for (mut life, MaxLife(max_life)) in &mut query {
+        if life.0 > *max_life {
+            life.0 = *max_life;
+        }
+    }
+}
+
+fn check_player_died(
+    query: Query<&Life, (With<Player>, Changed<Life>)>,
+    mut writer: EventWriter<PlayerDied>,
+) {
+    if let Ok(Life(0)) = query.get_single() {
+        writer.send(PlayerDied);
+    }
+}
+
+fn game_over(mut app_state: ResMut<NextState<AppState>>) {
+    app_state.set(AppState::GameOver);
+}
+
 fn move_player(
     keyboard_input: Res<Input<KeyCode>>,
     time: Res<Time>,
-    mut player_query: Query<(&mut KinematicCharacterController, &Speed), With<Player>>,
+    mut player_query: Query<
+        (&mut KinematicCharacterController, &Speed, &mut Knockback),
+        With<Player>,
+    >,
 ) {
-    let (mut controller, Speed(speed)) = player_query.single_mut();
+    let (mut controller, Speed(speed), mut knockback) = player_query.single_mut();
 
     let axis = input_as_axis(
         keyboard_input,
@@ -103,84 +223,224 @@ fn move_player(
     );
 
     controller.translation = axis.map(|ax| ax.mul(*speed) * time.delta_seconds());
+
+    if knockback.current > 0. {
+        let push = knockback.velocity * time.delta_seconds();
+        controller.translation = Some(controller.translation.unwrap_or_default() + push);
+        knockback.current -= time.delta_seconds();
+    }
+}
+
+/**
+ * Aims with the arrow keys, the right stick of a gamepad or the mouse cursor, in that order.
+ * The cursor only takes over when it moves, so it doesn't fight the other two.
+ */
+fn aim_player(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut player: Query<(&mut AimDirection, &Transform), With<Player>>,
+) {
+    let cursor_position = cursor_moved.read().last().map(|event| event.position);
+    let Ok((mut aim_direction, transform)) = player.get_single_mut() else {
+        return;
+    };
+
+    let keys = input_as_axis(
+        keyboard_input,
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::Up,
+        KeyCode::Down,
+    );
+
+    let stick = || {
+        stick_as_axis(
+            &gamepads,
+            &axes,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        )
+    };
+
+    let cursor = || {
+        let (camera, camera_transform) = camera.get_single().ok()?;
+        let target = camera.viewport_to_world_2d(camera_transform, cursor_position?)?;
+
+        (target - transform.translation.truncate()).try_normalize()
+    };
+
+    if let Some(direction) = keys.or_else(stick).or_else(cursor) {
+        aim_direction.0 = direction;
+    }
 }
 
 fn use_item_player(
     keyboard_input: Res<Input<KeyCode>>,
-    mut inventory: Query<(&mut Inventory, &Transform), With<Player>>,
+    inventory: Query<(&Inventory, &Transform, &AimDirection), With<Player>>,
     mut writer: EventWriter<ItemEvent>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Space) {
-        if let Ok((mut inventory, transform)) = inventory.get_single_mut() {
+        if let Ok((inventory, transform, AimDirection(direction))) = inventory.get_single() {
             let position = transform.translation.truncate();
-            use_active_item(&mut inventory, &position, &transform.rotation, &mut writer);
+            use_active_item(inventory, &position, direction, &mut writer);
         }
     }
 }
 
-fn read_touching_door_system(
+fn drop_item_player(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut inventory: Query<(&mut Inventory, &Transform), With<Player>>,
+    mut writer: EventWriter<ItemEvent>,
+    mut inventory_writer: EventWriter<InventoryEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Q) {
+        if let Ok((mut inventory, transform)) = inventory.get_single_mut() {
+            if let Some(entity) = inventory.take_active() {
+                inventory_writer.send(InventoryEvent::Removed(entity));
+                writer.send(ItemEvent::Dropped {
+                    entity,
+                    position: transform.translation.truncate(),
+                });
+            }
+        }
+    }
+}
+
+fn walk_through_door_system(
     context: Res<RapierContext>,
-    query_player: Query<Entity, With<Player>>,
-    query_door: Query<Entity, With<Door>>,
+    mut dungeon: ResMut<Dungeon>,
+    mut query_player: Query<(Entity, &mut Transform), With<Player>>,
+    query_door: Query<&Door>,
+    mut room_exited: EventWriter<RoomExited>,
+    mut room_entered: EventWriter<RoomEntered>,
 ) {
     console_log("Touching door", "false");
 
-    if let Ok(player) = query_player.get_single() {
-        let pairs = context.intersection_pairs_with(player);
+    if let Ok((player, mut transform)) = query_player.get_single_mut() {
+        let door = context
+            .intersection_pairs_with(player)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .find_map(|(a, b, _)| query_door.get(if a == player { b } else { a }).ok());
+
+        if let Some(door) = door {
+            console_log("Touching door", "true");
+
+            let (dx, dy) = door.offset();
+            let x = dungeon.current_room_x + dx;
+            let y = dungeon.current_room_y + dy;
 
-        for (_, entity, _) in pairs {
-            if query_door.contains(entity) {
-                console_log("Touching door", "true");
+            if dungeon.get_room(x, y).is_some() {
+                room_exited.send(RoomExited {
+                    coord_x: dungeon.current_room_x,
+                    coord_y: dungeon.current_room_y,
+                });
+                room_entered.send(RoomEntered {
+                    coord_x: x,
+                    coord_y: y,
+                    door: Some(door.opposite()),
+                });
+
+                let position = door.opposite().spawn_point();
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+
+                dungeon.current_room_x = x;
+                dungeon.current_room_y = y;
+                console_log("Room", format!("{}, {}", x, y));
             }
         }
     }
 }
 
-fn use_active_item(
-    inventory: &mut Inventory,
-    position: &Vec2,
-    rotation: &Quat,
-    writer: &mut EventWriter<ItemEvent>,
+/**
+ * Generates the next, harder floor when the player steps on the stairs.
+ * The player entity is kept, so its inventory and life carry over, but items left lying
+ * around on the floor are gone for good.
+ */
+fn take_stairs(
+    mut commands: Commands,
+    context: Res<RapierContext>,
+    mut dungeon: ResMut<Dungeon>,
+    query_player: Query<Entity, With<Player>>,
+    query_stairs: Query<(), With<Stairs>>,
+    mut room_exited: EventWriter<RoomExited>,
+    mut room_entered: EventWriter<RoomEntered>,
+    mut floor_entered: EventWriter<FloorEntered>,
 ) {
-    let event = |entity: &Entity| ItemEvent::Used {
-        entity: entity.clone(),
-        position: position.clone(),
-        rotation: rotation.clone(),
-    };
+    if let Ok(player) = query_player.get_single() {
+        let on_stairs = context
+            .intersection_pairs_with(player)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .any(|(a, b, _)| query_stairs.contains(if a == player { b } else { a }));
 
-    match inventory {
-        Inventory::DoubleHanded(Some(entity), _) => {
-            writer.send(event(entity));
-        }
-        Inventory::Revolver(entities) => {
-            if let Some(entity) = entities.front() {
-                writer.send(event(entity));
+        if on_stairs {
+            room_exited.send(RoomExited {
+                coord_x: dungeon.current_room_x,
+                coord_y: dungeon.current_room_y,
+            });
+
+            for room in &dungeon.layout {
+                for (item, _) in &room.items {
+                    commands.entity(*item).despawn_recursive();
+                }
             }
 
-            entities.rotate_left(1);
-        }
-        Inventory::OneHanded(Some(entity)) => {
-            writer.send(event(entity));
+            let floor = dungeon.floor + 1;
+            *dungeon = Dungeon::generate(dungeon.seed, &DungeonConfig::for_floor(floor));
+
+            room_entered.send(RoomEntered {
+                coord_x: dungeon.current_room_x,
+                coord_y: dungeon.current_room_y,
+                door: None,
+            });
+            floor_entered.send(FloorEntered { floor });
+            console_log("Floor", floor);
         }
-        Inventory::DoubleHanded(None, _) | Inventory::OneHanded(None) => {}
-    };
+    }
 }
 
-fn r(
+fn pick_up_key(
+    mut commands: Commands,
     context: Res<RapierContext>,
-    query_player: Query<Entity, With<Player>>,
-    query_door: Query<(&Door, Entity), With<Door>>,
-    // query_dungeon: Query<Entity, With<Player>>,
+    mut dungeon: ResMut<Dungeon>,
+    mut query_player: Query<(Entity, &mut Keys), With<Player>>,
+    query_key: Query<(), With<Key>>,
 ) {
-    console_log("Touching door", "false");
+    if let Ok((player, mut keys)) = query_player.get_single_mut() {
+        let picked_up = context
+            .intersection_pairs_with(player)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(a, b, _)| if a == player { b } else { a })
+            .filter(|entity| query_key.contains(*entity));
 
-    if let Ok(player) = query_player.get_single() {
-        let pairs = context.intersection_pairs_with(player);
+        for key in picked_up {
+            commands.entity(key).despawn_recursive();
+            keys.0 += 1;
 
-        for (_, entity, _) in pairs {
-            if let Ok((door, _)) = query_door.get(entity) {
-                console_log("Touching door", "true");
+            if let Some(room) = dungeon.get_current_room_mut() {
+                room.key = None;
             }
         }
     }
 }
+
+/**
+ * Uses every active item: the single one, both hands, or the revolver's front item.
+ */
+fn use_active_item(
+    inventory: &Inventory,
+    position: &Vec2,
+    direction: &Vec2,
+    writer: &mut EventWriter<ItemEvent>,
+) {
+    for entity in inventory.active_items() {
+        writer.send(ItemEvent::Used {
+            entity,
+            position: position.clone(),
+            direction: direction.clone(),
+        });
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * Small deterministic random number generator (SplitMix64).
+ * Everything procedural goes through this so a run can be replayed from its seed.
+ */
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        return Rng { state: seed };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        return z ^ (z >> 31);
+    }
+
+    /**
+     * Returns a number in `0..bound`. `bound` must not be zero.
+     */
+    pub fn below(&mut self, bound: usize) -> usize {
+        return (self.next_u64() % bound as u64) as usize;
+    }
+
+    /**
+     * Returns a number in `0.0..1.0`.
+     */
+    pub fn next_f32(&mut self) -> f32 {
+        return (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        return self.next_f32() < probability;
+    }
+}
+
+/**
+ * Seed for a fresh run, taken from the system clock.
+ */
+pub fn random_seed() -> u64 {
+    return SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+}
@@ -0,0 +1,150 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    constants::{TILE_COLUMNS, TILE_ROWS},
+    enemies::EnemyKind,
+    obstacles::Obstacle,
+    rooms::RoomKind,
+};
+
+/**
+ * Describes the contents of a room. Authored as `.room.ron` files in `assets/rooms`
+ * and hot-reloaded while the game is running.
+ */
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct RoomTemplate {
+    pub floor: String,
+    /// Where the player is placed when starting on this room rather than walking in.
+    #[serde(default)]
+    pub player_spawn: Vec2,
+    #[serde(default)]
+    pub enemy_spawners: Vec<EnemySpawner>,
+    #[serde(default)]
+    pub pedestals: Vec<Vec2>,
+    #[serde(default)]
+    pub shopkeeper: Option<Vec2>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleTemplate>,
+    /// Where the stairs down appear once a boss room is cleared.
+    #[serde(default)]
+    pub stairs: Vec2,
+    /// Where a key lands if the room drops one when cleared.
+    #[serde(default)]
+    pub key: Vec2,
+}
+
+/**
+ * An obstacle on the room's tile grid, `tile` being `(column, row)` from the bottom left.
+ */
+#[derive(Debug, Deserialize)]
+pub struct ObstacleTemplate {
+    pub kind: Obstacle,
+    pub tile: (u32, u32),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnemySpawner {
+    pub position: Vec2,
+    #[serde(default)]
+    pub kind: EnemyKind,
+}
+
+/**
+ * Keeps every room template loaded so rooms can spawn without waiting on the asset server.
+ */
+#[derive(Resource, Default)]
+pub struct RoomTemplates(pub HashMap<String, Handle<RoomTemplate>>);
+
+#[derive(Default)]
+pub struct RoomTemplateLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum RoomTemplateLoaderError {
+    #[error("Could not read room template: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse room template: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error(
+        "Obstacle tile {0:?} is outside of the {}x{} room grid",
+        TILE_COLUMNS,
+        TILE_ROWS
+    )]
+    TileOutOfBounds((u32, u32)),
+}
+
+impl AssetLoader for RoomTemplateLoader {
+    type Asset = RoomTemplate;
+    type Settings = ();
+    type Error = RoomTemplateLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<RoomTemplate, RoomTemplateLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let template = ron::de::from_bytes::<RoomTemplate>(&bytes)?;
+
+            if let Some(obstacle) = template
+                .obstacles
+                .iter()
+                .find(|obstacle| obstacle.tile.0 >= TILE_COLUMNS || obstacle.tile.1 >= TILE_ROWS)
+            {
+                return Err(RoomTemplateLoaderError::TileOutOfBounds(obstacle.tile));
+            }
+
+            return Ok(template);
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["room.ron"]
+    }
+}
+
+impl RoomKind {
+    /**
+     * Templates the dungeon generator picks from for a room of this kind.
+     */
+    pub fn templates(&self) -> &'static [&'static str] {
+        match self {
+            RoomKind::Start => &["rooms/start.room.ron"],
+            RoomKind::Combat => &["rooms/combat-1.room.ron", "rooms/combat-2.room.ron"],
+            RoomKind::Treasure => &["rooms/treasure.room.ron"],
+            RoomKind::Shop => &["rooms/shop.room.ron"],
+            RoomKind::Boss => &["rooms/boss.room.ron"],
+            RoomKind::Secret => &["rooms/secret.room.ron"],
+        }
+    }
+}
+
+pub fn load_room_templates(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let kinds = [
+        RoomKind::Start,
+        RoomKind::Combat,
+        RoomKind::Treasure,
+        RoomKind::Shop,
+        RoomKind::Boss,
+        RoomKind::Secret,
+    ];
+
+    let templates = kinds
+        .iter()
+        .flat_map(|kind| kind.templates())
+        .map(|path| (path.to_string(), asset_server.load::<RoomTemplate>(*path)))
+        .collect();
+
+    commands.insert_resource(RoomTemplates(templates));
+}
@@ -1,7 +1,18 @@
-use crate::constants::{self, DOOR_WIDTH, TOP_WALL, WALL_WIDTH};
+use crate::constants::{self, DOOR_WIDTH, PEDESTAL_SIZE, TOP_WALL, WALL_WIDTH};
 use crate::doors::*;
+use crate::dungeon::Dungeon;
+use crate::enemies::EnemyBundle;
+use crate::items::{definition::ItemRegistry, pickup::PickupBundle};
+use crate::obstacles::ObstacleBundle;
+use crate::player::Life;
+use crate::rng::Rng;
+use crate::room_templates::RoomTemplate;
+use crate::run::GameplayEntity;
 use bevy::{prelude::*, sprite::Anchor};
-use bevy_rapier2d::{dynamics::RigidBody, geometry::Collider};
+use bevy_rapier2d::{
+    dynamics::RigidBody,
+    geometry::{Collider, Sensor},
+};
 
 pub enum WallLocation {
     TopLeft,
@@ -17,51 +28,308 @@ pub struct WallBundle {
     collider: Collider,
 }
 
+/**
+ * Tags everything a room spawns so it can be torn down when the player leaves.
+ */
+#[derive(Component, Default)]
+pub struct RoomEntities;
+
+/**
+ * Everything a room spawns belongs to both the room and the run.
+ */
+#[derive(Bundle, Default)]
+pub struct RoomEntityBundle {
+    room_entities: RoomEntities,
+    gameplay_entity: GameplayEntity,
+}
+
+#[derive(Component)]
+pub struct Pedestal;
+
+#[derive(Component)]
+pub struct Shopkeeper;
+
+/**
+ * Leads down to the next floor. Only found in boss rooms once they are cleared.
+ */
+#[derive(Component)]
+pub struct Stairs;
+
+/**
+ * A key lying on the floor, picked up by walking over it.
+ */
+#[derive(Component)]
+pub struct Key;
+
+/**
+ * A solid, untextured prop placed inside a room.
+ */
+#[derive(Bundle)]
+pub struct PropBundle {
+    sprite_bundle: SpriteBundle,
+    body: RigidBody,
+    collider: Collider,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoomKind {
+    Start,
+    Combat,
+    Treasure,
+    Shop,
+    Boss,
+    Secret,
+}
+
 #[derive(Component)]
 pub struct Room {
     pub coord_x: isize,
     pub coord_y: isize,
+    pub kind: RoomKind,
     pub width: f32,
     pub height: f32,
-    pub floor: String,
+    /// Asset path of the `RoomTemplate` describing what's inside the room.
+    pub template: String,
+    /// Sides connected to a neighbouring room in the dungeon.
+    pub doors: Vec<Door>,
+    /// Doors leading into a secret room, drawn like the wall around them.
+    pub hidden_doors: Vec<Door>,
+    /// Set once the player has been inside the room.
+    pub visited: bool,
+    /// Set once the room has no living enemies left.
+    pub cleared: bool,
+    /// Set once the player has used a key on a door leading into this room.
+    pub unlocked: bool,
+    /// Items lying on the floor of this room and where they are.
+    pub items: Vec<(Entity, Vec2)>,
+    /// Where a key dropped in this room lies until the player picks it up.
+    pub key: Option<Vec2>,
+    /// Set once items have been put out in front of the room's pedestals.
+    pub stocked: bool,
+}
+
+impl RoomKind {
+    fn floor_tint(&self) -> Color {
+        match self {
+            RoomKind::Start | RoomKind::Combat => Color::WHITE,
+            RoomKind::Treasure => Color::rgb(1.0, 0.9, 0.6),
+            RoomKind::Shop => Color::rgb(0.8, 1.0, 0.8),
+            RoomKind::Boss => Color::rgb(1.0, 0.5, 0.5),
+            RoomKind::Secret => Color::rgb(0.5, 0.5, 0.7),
+        }
+    }
 }
 
 impl Room {
-    pub fn new(coord_x: isize, coord_y: isize, floor: String) -> Room {
+    pub fn new(
+        coord_x: isize,
+        coord_y: isize,
+        kind: RoomKind,
+        template: String,
+        doors: Vec<Door>,
+    ) -> Room {
         return Room {
             coord_x,
             coord_y,
+            kind,
             width: constants::FLOOR_WIDTH,
             height: constants::FLOOR_HEIGHT,
-            floor,
+            template,
+            doors,
+            hidden_doors: vec![],
+            visited: false,
+            cleared: false,
+            unlocked: false,
+            items: vec![],
+            key: None,
+            stocked: false,
         };
     }
 
-    pub fn spawn(&self, commands: &mut Commands, asset_server: ResMut<AssetServer>) {
-        let floor = asset_server.load::<Image>(&self.floor);
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        template: &RoomTemplate,
+        dungeon: &Dungeon,
+    ) {
+        let floor = asset_server.load::<Image>(&template.floor);
 
         // Walls
-        commands.spawn(WallBundle::new(WallLocation::TopRight));
-        commands.spawn(WallBundle::new(WallLocation::TopLeft));
-        commands.spawn(WallBundle::new(WallLocation::BottomRight));
-        commands.spawn(WallBundle::new(WallLocation::BottomLeft));
+        commands.spawn((
+            WallBundle::new(WallLocation::TopRight, &self.doors),
+            RoomEntityBundle::default(),
+        ));
+        commands.spawn((
+            WallBundle::new(WallLocation::TopLeft, &self.doors),
+            RoomEntityBundle::default(),
+        ));
+        commands.spawn((
+            WallBundle::new(WallLocation::BottomRight, &self.doors),
+            RoomEntityBundle::default(),
+        ));
+        commands.spawn((
+            WallBundle::new(WallLocation::BottomLeft, &self.doors),
+            RoomEntityBundle::default(),
+        ));
 
         // Floor
-        commands.spawn(SpriteBundle {
-            texture: floor,
-            transform: Transform::from_translation(Vec3::new(0., 0., 1.)),
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(self.width, self.height)),
+        commands.spawn((
+            SpriteBundle {
+                texture: floor,
+                transform: Transform::from_translation(Vec3::new(0., 0., 1.)),
+                sprite: Sprite {
+                    color: self.kind.floor_tint(),
+                    custom_size: Some(Vec2::new(self.width, self.height)),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            ..Default::default()
-        });
+            RoomEntityBundle::default(),
+        ));
 
         // Doors
-        commands.spawn(DoorBundle::new(Door::Left));
-        commands.spawn(DoorBundle::new(Door::Right));
-        commands.spawn(DoorBundle::new(Door::Bottom));
-        commands.spawn(DoorBundle::new(Door::Top));
+        for door in &self.doors {
+            let state = dungeon.door_state(self, *door);
+
+            let mut entity = if self.hidden_doors.contains(door) {
+                commands.spawn((
+                    DoorBundle::hidden(*door, state),
+                    HiddenDoor,
+                    RoomEntityBundle::default(),
+                ))
+            } else {
+                commands.spawn((DoorBundle::new(*door, state), RoomEntityBundle::default()))
+            };
+
+            if state.is_open() {
+                entity.insert(Sensor);
+            }
+        }
+
+        // Obstacles
+        for obstacle in &template.obstacles {
+            let (column, row) = obstacle.tile;
+            let mut entity = commands.spawn((
+                ObstacleBundle::new(obstacle.kind, column, row),
+                RoomEntityBundle::default(),
+            ));
+
+            if obstacle.kind.is_sensor() {
+                entity.insert(Sensor);
+            }
+
+            if let Some(life) = obstacle.kind.life() {
+                entity.insert(Life(life));
+            }
+        }
+
+        // Contents
+        for position in &template.pedestals {
+            commands.spawn((
+                PropBundle::new(*position, PEDESTAL_SIZE, constants::PEDESTAL_COLOR),
+                Pedestal,
+                RoomEntityBundle::default(),
+            ));
+        }
+
+        if let Some(position) = template.shopkeeper {
+            commands.spawn((
+                PropBundle::new(
+                    position,
+                    constants::PLAYER_SIZE,
+                    constants::SHOPKEEPER_COLOR,
+                ),
+                Shopkeeper,
+                RoomEntityBundle::default(),
+            ));
+        }
+
+        // Enemies
+        if !self.cleared {
+            for (i, spawner) in template.enemy_spawners.iter().enumerate() {
+                let seed = dungeon.seed
+                    ^ ((self.coord_x as u64) << 48)
+                    ^ ((self.coord_y as u64) << 32)
+                    ^ i as u64;
+
+                commands.spawn((
+                    EnemyBundle::new(spawner.kind, spawner.position, dungeon.floor, seed),
+                    RoomEntityBundle::default(),
+                ));
+            }
+        }
+
+        if self.kind == RoomKind::Boss && self.cleared {
+            self.spawn_stairs(commands, template);
+        }
+
+        if let Some(position) = self.key {
+            Room::spawn_key(commands, position);
+        }
+
+        for (item, position) in &self.items {
+            commands
+                .entity(*item)
+                .insert(PickupBundle::new(*position, true));
+        }
+    }
+
+    /**
+     * Puts a random registered item in front of every pedestal, the first time only.
+     * From then on they are part of the room's `items` like any other item on the floor.
+     */
+    pub fn stock_pedestals(
+        &mut self,
+        commands: &mut Commands,
+        asset_server: &AssetServer,
+        registry: &ItemRegistry,
+        template: &RoomTemplate,
+        seed: u64,
+    ) {
+        if self.stocked {
+            return;
+        }
+        self.stocked = true;
+
+        let names = registry.names();
+        if names.is_empty() {
+            return;
+        }
+
+        let mut rng =
+            Rng::new(seed ^ ((self.coord_x as u64) << 48) ^ ((self.coord_y as u64) << 32));
+
+        for pedestal in &template.pedestals {
+            let name = names[rng.below(names.len())];
+
+            if let Some(item) = registry.spawn(commands, asset_server, name) {
+                self.items
+                    .push((item, *pedestal - Vec2::new(0., PEDESTAL_SIZE.y)));
+            }
+        }
+    }
+
+    pub fn spawn_stairs(&self, commands: &mut Commands, template: &RoomTemplate) {
+        commands.spawn((
+            PropBundle::new(
+                template.stairs,
+                constants::STAIRS_SIZE,
+                constants::STAIRS_COLOR,
+            ),
+            Sensor,
+            Stairs,
+            RoomEntityBundle::default(),
+        ));
+    }
+
+    pub fn spawn_key(commands: &mut Commands, position: Vec2) {
+        commands.spawn((
+            PropBundle::new(position, constants::KEY_SIZE, constants::KEY_COLOR),
+            Sensor,
+            Key,
+            RoomEntityBundle::default(),
+        ));
     }
 }
 
@@ -84,18 +352,44 @@ impl WallLocation {
         }
     }
 
-    fn collider(&self) -> Collider {
+    /**
+     * The horizontal and vertical sides of the room this corner piece covers half of.
+     */
+    fn sides(&self) -> (Door, Door) {
+        match self {
+            WallLocation::TopLeft => (Door::Top, Door::Left),
+            WallLocation::TopRight => (Door::Top, Door::Right),
+            WallLocation::BottomLeft => (Door::Bottom, Door::Left),
+            WallLocation::BottomRight => (Door::Bottom, Door::Right),
+        }
+    }
+
+    /**
+     * Leaves half a door gap at the end of each side that has a door,
+     * otherwise runs all the way to the middle so the side is sealed.
+     */
+    fn collider(&self, doors: &[Door]) -> Collider {
+        let (horizontal, vertical) = self.sides();
+        let gap = |side: Door| {
+            if doors.contains(&side) {
+                DOOR_WIDTH / 2.
+            } else {
+                0.
+            }
+        };
+        let (horizontal_gap, vertical_gap) = (gap(horizontal), gap(vertical));
+
         match self {
             WallLocation::BottomRight => Collider::polyline(
                 vec![
                     Vec2::new(
-                        -constants::WALL_WIDTH + DOOR_WIDTH / 2.,
+                        -constants::WALL_WIDTH + horizontal_gap,
                         constants::WALL_THICKNESS,
                     ),
                     Vec2::new(-constants::WALL_THICKNESS, constants::WALL_THICKNESS),
                     Vec2::new(
                         -constants::WALL_THICKNESS,
-                        constants::WALL_HEIGHT - DOOR_WIDTH / 2.,
+                        constants::WALL_HEIGHT - vertical_gap,
                     ),
                 ],
                 None,
@@ -103,13 +397,13 @@ impl WallLocation {
             WallLocation::BottomLeft => Collider::polyline(
                 vec![
                     Vec2::new(
-                        constants::WALL_WIDTH - DOOR_WIDTH / 2.,
+                        constants::WALL_WIDTH - horizontal_gap,
                         constants::WALL_THICKNESS,
                     ),
                     Vec2::new(constants::WALL_THICKNESS, constants::WALL_THICKNESS),
                     Vec2::new(
                         constants::WALL_THICKNESS,
-                        constants::WALL_HEIGHT - DOOR_WIDTH / 2.,
+                        constants::WALL_HEIGHT - vertical_gap,
                     ),
                 ],
                 None,
@@ -117,24 +411,24 @@ impl WallLocation {
             WallLocation::TopRight => Collider::polyline(
                 vec![
                     Vec2::new(
-                        -constants::WALL_WIDTH + DOOR_WIDTH / 2.,
+                        -constants::WALL_WIDTH + horizontal_gap,
                         -constants::WALL_THICKNESS,
                     ),
                     Vec2::new(-constants::WALL_THICKNESS, -constants::WALL_THICKNESS),
                     Vec2::new(
                         -constants::WALL_THICKNESS,
-                        -constants::WALL_HEIGHT + DOOR_WIDTH / 2.,
+                        -constants::WALL_HEIGHT + vertical_gap,
                     ),
                 ],
                 None,
             ),
             WallLocation::TopLeft => Collider::polyline(
                 vec![
-                    Vec2::new(WALL_WIDTH - DOOR_WIDTH / 2., -constants::WALL_THICKNESS),
+                    Vec2::new(WALL_WIDTH - horizontal_gap, -constants::WALL_THICKNESS),
                     Vec2::new(constants::WALL_THICKNESS, -constants::WALL_THICKNESS),
                     Vec2::new(
                         constants::WALL_THICKNESS,
-                        -constants::WALL_HEIGHT + DOOR_WIDTH / 2.,
+                        -constants::WALL_HEIGHT + vertical_gap,
                     ),
                 ],
                 None,
@@ -144,7 +438,7 @@ impl WallLocation {
 }
 
 impl WallBundle {
-    pub fn new(location: WallLocation) -> WallBundle {
+    pub fn new(location: WallLocation, doors: &[Door]) -> WallBundle {
         WallBundle {
             sprite_bundle: SpriteBundle {
                 transform: Transform {
@@ -160,7 +454,25 @@ impl WallBundle {
                 ..default()
             },
             body: RigidBody::Fixed,
-            collider: location.collider(),
+            collider: location.collider(doors),
+        }
+    }
+}
+
+impl PropBundle {
+    pub fn new(position: Vec2, size: Vec2, color: Color) -> PropBundle {
+        PropBundle {
+            sprite_bundle: SpriteBundle {
+                transform: Transform::from_translation(position.extend(2.)),
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+            body: RigidBody::Fixed,
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
         }
     }
 }
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::{
+    combat::EnemyKilled,
+    constants::{AppState, GameState},
+    dungeon::{FloorEntered, RoomCleared},
+    items::components::ItemEvent,
+};
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NextRun>()
+            .add_systems(OnExit(AppState::Game), teardown_run)
+            .add_systems(
+                Update,
+                (
+                    tick_run_time.run_if(in_state(GameState::Running)),
+                    count_floors,
+                    count_cleared_rooms,
+                    count_killed_enemies,
+                    count_collected_items,
+                )
+                    .run_if(in_state(AppState::Game))
+                    .run_if(resource_exists::<RunStats>()),
+            );
+    }
+}
+
+/**
+ * Anything that belongs to the current run: the player, their items, room contents and
+ * projectiles. All of it is despawned when leaving `AppState::Game`.
+ */
+#[derive(Component, Default)]
+pub struct GameplayEntity;
+
+/**
+ * Marks items the player has picked up before, so picking them back up after dropping them
+ * doesn't count towards `RunStats::items_collected` again.
+ */
+#[derive(Component)]
+struct Collected;
+
+/**
+ * How the next run started from `SetupGame` should be generated.
+ * Without a seed a random one is picked.
+ */
+#[derive(Resource, Default)]
+pub struct NextRun {
+    pub seed: Option<u64>,
+}
+
+/**
+ * Progress of the current run, shown on the game over screen.
+ */
+#[derive(Resource)]
+pub struct RunStats {
+    pub seed: u64,
+    pub floor: u32,
+    pub rooms_cleared: u32,
+    pub enemies_killed: u32,
+    pub items_collected: u32,
+    /// Seconds spent playing, pauses excluded.
+    pub time: f32,
+}
+
+impl RunStats {
+    pub fn new(seed: u64) -> RunStats {
+        return RunStats {
+            seed,
+            floor: 1,
+            rooms_cleared: 0,
+            enemies_killed: 0,
+            items_collected: 0,
+            time: 0.,
+        };
+    }
+}
+
+fn teardown_run(
+    mut commands: Commands,
+    mut game_state: ResMut<NextState<GameState>>,
+    query: Query<Entity, With<GameplayEntity>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    game_state.set(GameState::Running);
+}
+
+fn tick_run_time(time: Res<Time>, mut stats: ResMut<RunStats>) {
+    stats.time += time.delta_seconds();
+}
+
+fn count_floors(mut reader: EventReader<FloorEntered>, mut stats: ResMut<RunStats>) {
+    for event in reader.read() {
+        stats.floor = event.floor;
+    }
+}
+
+/**
+ * Only rooms that had enemies to fight count, not the ones that were empty to begin with.
+ */
+fn count_cleared_rooms(mut reader: EventReader<RoomCleared>, mut stats: ResMut<RunStats>) {
+    stats.rooms_cleared += reader.read().filter(|event| event.had_enemies).count() as u32;
+}
+
+fn count_killed_enemies(mut reader: EventReader<EnemyKilled>, mut stats: ResMut<RunStats>) {
+    stats.enemies_killed += reader.read().count() as u32;
+}
+
+fn count_collected_items(
+    mut commands: Commands,
+    mut reader: EventReader<ItemEvent>,
+    mut stats: ResMut<RunStats>,
+    query: Query<(), With<Collected>>,
+) {
+    for event in reader.read() {
+        if let ItemEvent::PickedUp { entity } = event {
+            if !query.contains(*entity) {
+                stats.items_collected += 1;
+                commands.entity(*entity).insert(Collected);
+            }
+        }
+    }
+}
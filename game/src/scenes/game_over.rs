@@ -0,0 +1,101 @@
+use crate::constants::AppState;
+use crate::run::{NextRun, RunStats};
+use bevy::prelude::*;
+
+use styles::elements::*;
+use styles::stylesheet::*;
+use styles::*;
+
+#[derive(Component)]
+pub struct GameOverMenu;
+
+impl Plugin for GameOverMenu {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameOver), setup)
+            .add_systems(
+                Update,
+                (Retry::on_click(), NewRun::on_click(), ToMenu::on_click())
+                    .run_if(in_state(AppState::GameOver)),
+            )
+            .add_systems(
+                OnExit(AppState::GameOver),
+                despawn_recursively::<GameOverMenu>,
+            );
+    }
+}
+
+#[derive(Component, Clone)]
+struct Retry;
+on_click!(
+    Retry,
+    (ResMut<NextState<AppState>>, ResMut<NextRun>, Res<RunStats>),
+    |_, app_state, next_run, stats| {
+        next_run.seed = Some(stats.seed);
+        app_state.set(AppState::SetupGame);
+    }
+);
+
+#[derive(Component, Clone)]
+struct NewRun;
+on_click!(
+    NewRun,
+    (ResMut<NextState<AppState>>, ResMut<NextRun>),
+    |_, app_state, next_run| {
+        next_run.seed = None;
+        app_state.set(AppState::SetupGame);
+    }
+);
+
+#[derive(Component, Clone)]
+struct ToMenu;
+on_click!(ToMenu, (ResMut<NextState<AppState>>), |_, app_state| {
+    app_state.set(AppState::Splash);
+});
+
+fn menu_button(component: impl IntoElement, label: impl ToString) -> Element {
+    component.as_el(button(
+        cn!(w_full, bg_white, hover_(bg_red_600), pressed_(bg_red_800)),
+        text(cn!(text_5xl, text_black), label.to_string()),
+    ))
+}
+
+fn stat(label: impl ToString, value: impl ToString) -> Element {
+    div(
+        cn!(flex, w_full, justify_between),
+        [
+            text(cn!(text_2xl, text_white), label.to_string()),
+            text(cn!(text_2xl, text_white), value.to_string()),
+        ],
+    )
+}
+
+fn setup(mut commands: Commands, stats: Res<RunStats>) {
+    let minutes = (stats.time / 60.) as u32;
+    let seconds = stats.time as u32 % 60;
+
+    let tree = div(
+        cn!(h_full, w_full, flex, justify_center, items_center),
+        div(
+            cn!(flex, flex_col, gap_1),
+            [
+                text(cn!(text_5xl, text_white), "Game over"),
+                stat("Floor reached", stats.floor),
+                stat("Rooms cleared", stats.rooms_cleared),
+                stat("Enemies killed", stats.enemies_killed),
+                stat("Items collected", stats.items_collected),
+                stat("Time", format!("{}:{:02}", minutes, seconds)),
+                menu_button(Retry, "Retry with same seed"),
+                menu_button(NewRun, "New run"),
+                menu_button(ToMenu, "Main menu"),
+            ],
+        ),
+    );
+
+    spawn_root_element(&mut commands, GameOverMenu, tree);
+}
+
+fn despawn_recursively<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+}
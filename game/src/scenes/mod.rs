@@ -1,9 +1,11 @@
 mod debug_overlay;
+mod game_over;
 mod main_menu;
 mod pause_menu;
 mod player_overlay;
 
 pub use debug_overlay::*;
+pub use game_over::*;
 pub use main_menu::*;
 pub use pause_menu::*;
 pub use player_overlay::*;
@@ -1,7 +1,20 @@
+use crate::constants::AppSet;
 use crate::constants::AppState;
+use crate::constants::SOUL_HEART_COLOR;
+use crate::dungeon::Dungeon;
+use crate::dungeon::FloorEntered;
+use crate::dungeon::RoomEntered;
+use crate::inventory::Inventory;
+use crate::inventory::InventoryEvent;
+use crate::items::components::Cooldown;
+use crate::items::components::Icon;
 use crate::materials::outline_material::OutlineMaterial;
 use crate::player::Life;
+use crate::player::MaxLife;
 use crate::player::Player;
+use crate::player::SoulHearts;
+use crate::rooms::Room;
+use crate::rooms::RoomKind;
 use bevy::prelude::*;
 
 use styles::elements::*;
@@ -15,11 +28,77 @@ pub struct PlayerOverlay;
 impl Plugin for PlayerOverlay {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::Game), setup)
-            .add_systems(Update, (update_life).run_if(in_state(AppState::Game)))
+            .add_systems(
+                Update,
+                (
+                    update_hearts,
+                    update_life,
+                    update_cooldown_bar,
+                    update_inventory_bar.after(AppSet::Items),
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                Update,
+                (update_minimap, update_floor_label)
+                    .after(AppSet::Dungeon)
+                    .run_if(in_state(AppState::Game))
+                    .run_if(resource_exists::<Dungeon>()),
+            )
             .add_systems(OnExit(AppState::Game), despawn_recursively::<PlayerOverlay>);
     }
 }
 
+/**
+ * Regenerates the heart nodes when the number of containers or soul hearts changes.
+ */
+fn update_hearts(
+    mut commands: Commands,
+    player: Query<
+        (&Life, &MaxLife, &SoulHearts),
+        (With<Player>, Or<(Changed<MaxLife>, Changed<SoulHearts>)>),
+    >,
+    hearts: Query<Entity, With<Hearts>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<OutlineMaterial>>,
+) {
+    let (Ok((Life(life), MaxLife(max_life), SoulHearts(soul_hearts))), Ok(entity)) =
+        (player.get_single(), hearts.get_single())
+    else {
+        return;
+    };
+
+    let texture = asset_server.load::<Image>("textures/heart.png");
+    let mut nodes = vec![];
+
+    for n in 0..max_life.div_ceil(2) {
+        let material = materials.add(heart_material(
+            texture.clone(),
+            Color::WHITE,
+            heart_fill(*life, n),
+        ));
+        nodes.push(mat_heart(n, material));
+    }
+
+    for n in 0..soul_hearts.div_ceil(2) {
+        let material = materials.add(heart_material(
+            texture.clone(),
+            SOUL_HEART_COLOR,
+            heart_fill(*soul_hearts, n),
+        ));
+        nodes.push(mat(cn!(h_12, w_12), material));
+    }
+
+    commands
+        .entity(entity)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for node in nodes {
+                spawn_element(parent, node);
+            }
+        });
+}
+
 fn update_life(
     player: Query<&Life, (With<Player>, Changed<Life>)>,
     hearts: Query<(&Heart, &Handle<OutlineMaterial>), With<Handle<OutlineMaterial>>>,
@@ -28,46 +107,290 @@ fn update_life(
     if let Ok(Life(life)) = player.get_single() {
         for (heart, handle) in &hearts {
             let material = ui_materials.get_mut(handle).unwrap();
-            if life < &heart.0 {
-                material.image_tint = Color::BLACK;
-            } else {
-                material.image_tint = Color::WHITE;
-            }
+            material.fill = heart_fill(*life, heart.0);
         }
     }
 }
 
+#[derive(Component, Clone)]
+struct Hearts;
+
+/**
+ * Heart container `n`, counted from zero. Each one holds two points of `Life`.
+ */
 #[derive(Component, Clone, Debug)]
 struct Heart(u32);
 
+/**
+ * How much of heart `n` is filled when holding `half_hearts`, either 0, 0.5 or 1.
+ */
+fn heart_fill(half_hearts: u32, n: u32) -> f32 {
+    return half_hearts.saturating_sub(n * 2).min(2) as f32 / 2.;
+}
+
+fn heart_material(texture: Handle<Image>, tint: Color, fill: f32) -> OutlineMaterial {
+    return OutlineMaterial {
+        outline_color: Color::WHITE,
+        image_tint: tint,
+        thickness: 0.04,
+        fill,
+        color_texture: texture,
+    };
+}
+
 fn mat_heart<T: UiMaterial>(n: u32, material: Handle<T>) -> Element {
     Heart(n).as_el(mat(cn!(h_12, w_12), material.clone()))
 }
 
-fn setup(
+#[derive(Component, Clone)]
+struct CooldownBar;
+
+/**
+ * Fills the bar under the hearts as the active item's cooldown runs out.
+ */
+fn update_cooldown_bar(
+    player: Query<&Inventory, With<Player>>,
+    cooldowns: Query<&Cooldown>,
+    mut bar: Query<&mut Style, With<CooldownBar>>,
+) {
+    let progress = player
+        .get_single()
+        .ok()
+        .and_then(|inventory| inventory.active())
+        .and_then(|entity| cooldowns.get(entity).ok())
+        .map_or(1., |cooldown| cooldown.progress());
+
+    for mut style in &mut bar {
+        style.width = Val::Percent(progress * 100.);
+    }
+}
+
+#[derive(Component, Clone)]
+struct InventoryBar;
+
+/**
+ * Rebuilds the item slots whenever the inventory changes. Slots holding an item that gets
+ * used are highlighted: the single hand, both hands, or the front of the revolver.
+ */
+fn update_inventory_bar(
     mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
-    mut materials: ResMut<Assets<OutlineMaterial>>,
+    mut inventory_event: EventReader<InventoryEvent>,
+    added: Query<(), Added<InventoryBar>>,
+    bar: Query<Entity, With<InventoryBar>>,
+    player: Query<&Inventory, With<Player>>,
+    icons: Query<&Icon>,
 ) {
-    let full = asset_server.load::<Image>("textures/heart.png");
+    let changed = inventory_event.read().count() > 0;
+    let (Ok(entity), Ok(inventory)) = (bar.get_single(), player.get_single()) else {
+        return;
+    };
 
-    let base = OutlineMaterial {
-        outline_color: Color::WHITE,
-        image_tint: Color::WHITE,
-        thickness: 0.04,
-        color_texture: full.clone(),
+    if !changed && added.is_empty() {
+        return;
+    }
+
+    let active = inventory.active_items();
+    let slots = inventory
+        .slots()
+        .into_iter()
+        .map(|slot| {
+            let icon = match slot.and_then(|item| icons.get(item).ok()) {
+                Some(Icon(icon)) => vec![img(cn!(w_12, h_12), icon.clone())],
+                None => vec![],
+            };
+
+            if slot.is_some_and(|item| active.contains(&item)) {
+                return div(
+                    cn!(flex, w_16, h_16, justify_center, items_center, bg_white),
+                    icon,
+                );
+            }
+
+            return div(
+                cn!(flex, w_16, h_16, justify_center, items_center, bg_gray_700),
+                icon,
+            );
+        })
+        .collect::<Vec<_>>();
+
+    commands
+        .entity(entity)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for slot in slots {
+                spawn_element(parent, slot);
+            }
+        });
+}
+
+#[derive(Component, Clone)]
+struct Minimap;
+
+/**
+ * Rebuilds the minimap whenever the player moves to another room.
+ * Only visited rooms and the unvisited rooms next to them are shown.
+ */
+fn update_minimap(
+    mut commands: Commands,
+    mut room_entered: EventReader<RoomEntered>,
+    added: Query<(), Added<Minimap>>,
+    minimap: Query<Entity, With<Minimap>>,
+    dungeon: Res<Dungeon>,
+    asset_server: Res<AssetServer>,
+) {
+    let entered = room_entered.read().count() > 0;
+    let Ok(entity) = minimap.get_single() else {
+        return;
     };
 
-    let heart_1 = materials.add(base.clone());
-    let heart_2 = materials.add(base.clone());
-    let heart_3 = materials.add(base.clone());
+    if !entered && added.is_empty() {
+        return;
+    }
+
+    let known = dungeon
+        .layout
+        .iter()
+        .filter(|room| room.visited || is_discovered(&dungeon, room))
+        .collect::<Vec<_>>();
+
+    if known.is_empty() {
+        return;
+    }
+
+    let min_x = known.iter().map(|room| room.coord_x).min().unwrap();
+    let max_x = known.iter().map(|room| room.coord_x).max().unwrap();
+    let min_y = known.iter().map(|room| room.coord_y).min().unwrap();
+    let max_y = known.iter().map(|room| room.coord_y).max().unwrap();
+
+    // Rows are built top to bottom so higher rooms end up higher on screen.
+    let rows = (min_y..=max_y)
+        .rev()
+        .map(|y| {
+            let cells = (min_x..=max_x)
+                .map(|x| {
+                    let room = known
+                        .iter()
+                        .find(|room| room.coord_x == x && room.coord_y == y);
+
+                    match room {
+                        Some(room) => minimap_room(&dungeon, room, &asset_server),
+                        None => div(cn!(w_8, h_6), []),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            div(cn!(flex, flex_row, gap_1), cells)
+        })
+        .collect::<Vec<_>>();
+
+    commands
+        .entity(entity)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for row in rows {
+                spawn_element(parent, row);
+            }
+        });
+}
+
+#[derive(Component, Clone)]
+struct FloorLabel;
+
+fn update_floor_label(
+    mut floor_entered: EventReader<FloorEntered>,
+    added: Query<(), Added<FloorLabel>>,
+    mut label: Query<&mut Text, With<FloorLabel>>,
+    dungeon: Res<Dungeon>,
+) {
+    let entered = floor_entered.read().count() > 0;
+
+    if !entered && added.is_empty() {
+        return;
+    }
+
+    for mut text in &mut label {
+        text.sections[0].value = format!("Floor {}", dungeon.floor);
+    }
+}
+
+/**
+ * Unvisited rooms show up once the player has been next to them.
+ * Secret rooms stay hidden until they are found.
+ */
+fn is_discovered(dungeon: &Dungeon, room: &Room) -> bool {
+    if room.kind == RoomKind::Secret {
+        return false;
+    }
+
+    return dungeon
+        .neighbours(room.coord_x, room.coord_y)
+        .iter()
+        .any(|neighbour| neighbour.visited);
+}
+
+fn minimap_room(dungeon: &Dungeon, room: &Room, asset_server: &AssetServer) -> Element {
+    let is_current =
+        room.coord_x == dungeon.current_room_x && room.coord_y == dungeon.current_room_y;
+
+    let icon = match room.kind.minimap_icon() {
+        Some(path) => vec![img(cn!(w_6, h_6), asset_server.load(path))],
+        None => vec![],
+    };
+
+    if is_current {
+        return div(
+            cn!(flex, w_8, h_6, justify_center, items_center, bg_white),
+            icon,
+        );
+    }
+
+    if room.visited {
+        return div(
+            cn!(flex, w_8, h_6, justify_center, items_center, bg_gray_400),
+            icon,
+        );
+    }
+
+    return div(
+        cn!(flex, w_8, h_6, justify_center, items_center, bg_gray_700),
+        icon,
+    );
+}
+
+impl RoomKind {
+    fn minimap_icon(&self) -> Option<&'static str> {
+        match self {
+            RoomKind::Boss => Some("textures/minimap/boss.png"),
+            RoomKind::Treasure => Some("textures/minimap/treasure.png"),
+            RoomKind::Shop => Some("textures/minimap/shop.png"),
+            RoomKind::Secret => Some("textures/minimap/secret.png"),
+            RoomKind::Start | RoomKind::Combat => None,
+        }
+    }
+}
 
+fn setup(mut commands: Commands) {
     let tree = div(
-        cn!(flex, p_4),
+        cn!(flex, w_full, justify_between, items_start, p_4),
         [
-            mat_heart(1, heart_1),
-            mat_heart(2, heart_2),
-            mat_heart(3, heart_3),
+            div(
+                cn!(flex, flex_col, gap_1),
+                [
+                    Hearts.as_el(div(cn!(flex), [])),
+                    div(
+                        cn!(w_16, h_2, bg_gray_700),
+                        [CooldownBar.as_el(div(cn!(h_full, bg_white), []))],
+                    ),
+                    InventoryBar.as_el(div(cn!(flex, flex_row, gap_1), [])),
+                ],
+            ),
+            div(
+                cn!(flex, flex_col, items_end, gap_1),
+                [
+                    FloorLabel.as_el(text(cn!(text_2xl, text_white), "")),
+                    Minimap.as_el(div(cn!(flex, flex_col, gap_1), [])),
+                ],
+            ),
         ],
     );
 
@@ -195,6 +195,18 @@ node_style!(w_full, |_, style| {
     style.width = Val::Percent(100.0);
 });
 
+#[derive(Clone)]
+pub struct w_6;
+node_style!(w_6, |_, style| {
+    style.width = Val::Px(24.0);
+});
+
+#[derive(Clone)]
+pub struct w_8;
+node_style!(w_8, |_, style| {
+    style.width = Val::Px(32.0);
+});
+
 #[derive(Clone)]
 pub struct w_12;
 node_style!(w_12, |_, style| {
@@ -221,6 +233,18 @@ node_style!(h_full, |_, style| {
     style.height = Val::Percent(100.0);
 });
 
+#[derive(Clone)]
+pub struct h_2;
+node_style!(h_2, |_, style| {
+    style.height = Val::Px(8.0);
+});
+
+#[derive(Clone)]
+pub struct h_6;
+node_style!(h_6, |_, style| {
+    style.height = Val::Px(24.0);
+});
+
 #[derive(Clone)]
 pub struct h_12;
 node_style!(h_12, |_, style| {
@@ -296,6 +320,15 @@ node_style!(justify_end, |_, style| {
     style.justify_content = JustifyContent::FlexEnd;
 });
 
+// GAP
+
+#[derive(Clone)]
+pub struct gap_1;
+node_style!(gap_1, |_, style| {
+    style.row_gap = Val::Px(4.0);
+    style.column_gap = Val::Px(4.0);
+});
+
 // TEXT COLORS
 
 #[derive(Clone)]
@@ -324,6 +357,18 @@ node_background_color!(bg_black, |_, background_color| {
     background_color.0 = Color::BLACK;
 });
 
+#[derive(Clone)]
+pub struct bg_gray_400;
+node_background_color!(bg_gray_400, |_, background_color| {
+    background_color.0 = Color::rgb_u8(156, 163, 175);
+});
+
+#[derive(Clone)]
+pub struct bg_gray_700;
+node_background_color!(bg_gray_700, |_, background_color| {
+    background_color.0 = Color::rgb_u8(55, 65, 81);
+});
+
 #[derive(Clone)]
 pub struct bg_red_50;
 node_background_color!(bg_red_50, |_, background_color| {